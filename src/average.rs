@@ -0,0 +1,5 @@
+mod ema;
+mod swa;
+
+pub use ema::*;
+pub use swa::*;
@@ -0,0 +1,31 @@
+use crate::network::Network;
+
+pub struct Ema {
+    decay: f32,
+    params: Vec<f32>,
+}
+
+impl Ema {
+    pub fn new(decay: f32, network: &Network) -> Self {
+        Self {
+            decay,
+            params: network.param_buffer().to_vec(),
+        }
+    }
+
+    pub fn update(&mut self, params: &[f32]) {
+        assert!(params.len() == self.params.len());
+
+        for (average, param) in self.params.iter_mut().zip(params.iter()) {
+            *average = self.decay * *average + (1.0 - self.decay) * param;
+        }
+    }
+
+    pub fn reset(&mut self, params: &[f32]) {
+        self.params.copy_from_slice(params);
+    }
+
+    pub fn params(&self) -> &[f32] {
+        &self.params
+    }
+}
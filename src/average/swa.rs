@@ -0,0 +1,54 @@
+// running mean of the weights at the end of each of the last num_epochs epochs of fit
+pub struct Swa {
+    num_epochs: u32,
+    num_averaged: u32,
+    params: Vec<f32>,
+}
+
+impl Swa {
+    pub fn new(num_epochs: u32) -> Self {
+        assert!(num_epochs > 0);
+
+        Self {
+            num_epochs,
+            num_averaged: 0,
+            params: Vec::new(),
+        }
+    }
+
+    pub fn num_epochs(&self) -> u32 {
+        self.num_epochs
+    }
+
+    pub fn update(&mut self, params: &[f32]) {
+        if self.num_averaged == 0 {
+            self.params = params.to_vec();
+        } else {
+            assert!(params.len() == self.params.len());
+
+            let n = (self.num_averaged + 1) as f32;
+            for (average, param) in self.params.iter_mut().zip(params.iter()) {
+                *average += (param - *average) / n;
+            }
+        }
+        self.num_averaged += 1;
+    }
+
+    pub fn num_averaged(&self) -> u32 {
+        self.num_averaged
+    }
+
+    pub fn params(&self) -> Option<&[f32]> {
+        if self.num_averaged == 0 {
+            None
+        } else {
+            Some(&self.params)
+        }
+    }
+
+    // restores the average of num_averaged snapshots, e.g. from a checkpoint
+    pub fn load(&mut self, params: Vec<f32>, num_averaged: u32) {
+        self.params = params;
+        self.num_averaged = num_averaged;
+    }
+}
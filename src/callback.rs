@@ -0,0 +1,160 @@
+mod early_stopping;
+mod metrics_logger;
+mod model_checkpoint;
+mod tensorboard_logger;
+
+pub use early_stopping::EarlyStopping;
+pub use metrics_logger::{LogFormat, MetricsLogger};
+pub use model_checkpoint::ModelCheckpoint;
+pub use tensorboard_logger::TensorBoardLogger;
+
+use std::io;
+
+use crate::{eval::EvalReport, trainer::Trainer};
+
+pub struct BatchMetrics {
+    pub epoch: u32,
+    pub batch: u32,
+    pub num_batches: u32,
+    // mean loss of the samples in the batch
+    pub loss: f32,
+    pub accuracy: f32,
+    pub lr: f32,
+    // see Trainer::grad_norm
+    pub grad_norm: f32,
+    pub samples_per_second: f64,
+}
+
+pub struct EpochMetrics {
+    pub epoch: u32,
+    // mean loss over the samples of the epoch
+    pub loss: f32,
+    // training accuracy over the samples of the epoch
+    pub accuracy: f32,
+    pub lr: f32,
+    // evaluation of the validation set after the epoch
+    pub val: Option<EvalReport>,
+    pub seconds: f64,
+    pub samples_per_second: f64,
+}
+
+// an epoch metric watched by early stopping and checkpointing
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Monitor {
+    TrainLoss,
+    ValLoss,
+    ValAccuracy,
+    ValTopKAccuracy,
+    ValMacroF1,
+}
+
+impl Monitor {
+    pub fn value(self, metrics: &EpochMetrics) -> f32 {
+        match self {
+            Self::TrainLoss => metrics.loss,
+            _ => self.report_value(
+                metrics
+                    .val
+                    .as_ref()
+                    .expect("Please pass a validation set to fit to monitor a validation metric"),
+            ),
+        }
+    }
+
+    // value of a validation metric in an evaluation of the validation set
+    pub fn report_value(self, report: &EvalReport) -> f32 {
+        match self {
+            Self::TrainLoss => panic!("The training loss is not part of an evaluation"),
+            Self::ValLoss => report.loss,
+            Self::ValAccuracy => report.accuracy,
+            Self::ValTopKAccuracy => report.top_k_accuracy,
+            Self::ValMacroF1 => report.macro_f1(),
+        }
+    }
+
+    // losses improve by going down, the other metrics by going up
+    pub fn lower_is_better(self) -> bool {
+        matches!(self, Self::TrainLoss | Self::ValLoss)
+    }
+
+    pub fn improves(self, value: f32, best: Option<f32>, min_delta: f32) -> bool {
+        let Some(best) = best else {
+            return true;
+        };
+        if self.lower_is_better() {
+            value < best - min_delta
+        } else {
+            value > best + min_delta
+        }
+    }
+}
+
+// hooks called by Trainer::fit
+pub trait Callback {
+    fn on_batch_end(&mut self, _trainer: &Trainer, _metrics: &BatchMetrics) {}
+
+    fn on_epoch_end(&mut self, _trainer: &Trainer, _metrics: &EpochMetrics) {}
+
+    // called once every callback has run on_epoch_end, so the trainer's checkpoint
+    // includes the callbacks' updated state
+    fn after_epoch_end(&self, _trainer: &Trainer) {}
+
+    // checked after each epoch, fit stops as soon as any callback asks for it
+    fn should_stop(&self) -> bool {
+        false
+    }
+
+    // stored in the trainer's checkpoint so resumed training keeps it
+    fn state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    // fails if the state can't be loaded, so a checkpoint is checked before anything is applied
+    fn check_state(&self, _state: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn load_state(&mut self, _state: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct BatchEndFn<F>(F);
+
+impl<F> BatchEndFn<F>
+where
+    F: FnMut(&Trainer, &BatchMetrics),
+{
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+
+impl<F> Callback for BatchEndFn<F>
+where
+    F: FnMut(&Trainer, &BatchMetrics),
+{
+    fn on_batch_end(&mut self, trainer: &Trainer, metrics: &BatchMetrics) {
+        (self.0)(trainer, metrics);
+    }
+}
+
+pub struct EpochEndFn<F>(F);
+
+impl<F> EpochEndFn<F>
+where
+    F: FnMut(&Trainer, &EpochMetrics),
+{
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+
+impl<F> Callback for EpochEndFn<F>
+where
+    F: FnMut(&Trainer, &EpochMetrics),
+{
+    fn on_epoch_end(&mut self, trainer: &Trainer, metrics: &EpochMetrics) {
+        (self.0)(trainer, metrics);
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{self, ErrorKind};
+
+use crate::{
+    callback::{Callback, EpochMetrics, Monitor},
+    trainer::Trainer,
+};
+
+// stops fit once the monitored metric hasn't improved by more than min_delta for patience epochs
+pub struct EarlyStopping {
+    monitor: Monitor,
+    patience: u32,
+    min_delta: f32,
+    best: Option<f32>,
+    epochs_without_improvement: u32,
+}
+
+impl EarlyStopping {
+    pub fn new(monitor: Monitor, patience: u32, min_delta: f32) -> Self {
+        assert!(patience > 0);
+        assert!(min_delta >= 0.0);
+
+        Self {
+            monitor,
+            patience,
+            min_delta,
+            best: None,
+            epochs_without_improvement: 0,
+        }
+    }
+
+    pub fn best(&self) -> Option<f32> {
+        self.best
+    }
+}
+
+impl Callback for EarlyStopping {
+    fn on_epoch_end(&mut self, _trainer: &Trainer, metrics: &EpochMetrics) {
+        let value = self.monitor.value(metrics);
+        if self.monitor.improves(value, self.best, self.min_delta) {
+            self.best = Some(value);
+            self.epochs_without_improvement = 0;
+        } else {
+            self.epochs_without_improvement += 1;
+        }
+
+        if self.should_stop() {
+            println!(
+                "Stopping early after epoch {}, no improvement for {} epochs",
+                metrics.epoch, self.epochs_without_improvement
+            );
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.epochs_without_improvement >= self.patience
+    }
+
+    fn state(&self) -> Vec<u8> {
+        wincode::serialize(&(self.best, self.epochs_without_improvement))
+            .expect("Could not serialize the early stopping state")
+    }
+
+    fn check_state(&self, state: &[u8]) -> io::Result<()> {
+        wincode::deserialize::<(Option<f32>, u32)>(state)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        Ok(())
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> io::Result<()> {
+        (self.best, self.epochs_without_improvement) = wincode::deserialize(state)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DataPoint, network::NetworkBuilder, trainer::TrainerBuilder};
+
+    fn build_trainer() -> Trainer {
+        let mut network = NetworkBuilder::new(2).add_dense_layer(2).build();
+        network.param_buffer_mut().fill(0.1);
+        TrainerBuilder::new(network)
+            .batch_size(2)
+            .mse()
+            .sgd(0.1)
+            .seed(0)
+            // no improvement is ever large enough, so training stops after patience + 1 epochs
+            .early_stopping(Monitor::TrainLoss, 1, 1000.0)
+            .build()
+    }
+
+    #[test]
+    fn resumed_trainer_stays_stopped() {
+        let dataset = vec![
+            DataPoint {
+                input: vec![1.0, 0.0],
+                target: vec![0.0, 1.0],
+            },
+            DataPoint {
+                input: vec![0.0, 1.0],
+                target: vec![1.0, 0.0],
+            },
+        ];
+
+        let mut trainer = build_trainer();
+        trainer.set_show_progress(false);
+        trainer.fit(&dataset, None, 10).unwrap();
+        assert!(trainer.epoch() == 2);
+
+        let mut resumed = build_trainer();
+        resumed.set_show_progress(false);
+        resumed
+            .load_checkpoint(&trainer.checkpoint().unwrap())
+            .unwrap();
+        resumed.fit(&dataset, None, 10).unwrap();
+        assert!(resumed.epoch() == 2);
+    }
+}
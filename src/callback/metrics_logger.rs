@@ -0,0 +1,216 @@
+use std::{
+    fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    callback::{BatchMetrics, Callback, EpochMetrics},
+    trainer::Trainer,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Csv,
+    JsonLines,
+}
+
+// writes batch metrics to dir/batches.{csv,jsonl} and epoch metrics to dir/epochs.{csv,jsonl}
+// missing values are left empty in csv and written as null in json lines
+pub struct MetricsLogger {
+    format: LogFormat,
+    batch_path: PathBuf,
+    epoch_path: PathBuf,
+    // opened on the first write, once it is known whether training was resumed
+    writers: Option<(BufWriter<File>, BufWriter<File>)>,
+    append: bool,
+    log_every: u32,
+}
+
+const BATCH_COLUMNS: [&str; 7] = [
+    "epoch",
+    "batch",
+    "loss",
+    "accuracy",
+    "lr",
+    "grad_norm",
+    "samples_per_second",
+];
+
+const EPOCH_COLUMNS: [&str; 10] = [
+    "epoch",
+    "loss",
+    "accuracy",
+    "lr",
+    "val_loss",
+    "val_accuracy",
+    "val_top_k_accuracy",
+    "val_macro_f1",
+    "samples_per_second",
+    "seconds",
+];
+
+impl MetricsLogger {
+    // existing log files in dir are replaced, unless the trainer resumes from a checkpoint
+    // in which case they are appended to so the history is kept
+    pub fn new(dir: impl AsRef<Path>, format: LogFormat) -> io::Result<Self> {
+        let extension = match format {
+            LogFormat::Csv => "csv",
+            LogFormat::JsonLines => "jsonl",
+        };
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            format,
+            batch_path: dir.as_ref().join(format!("batches.{}", extension)),
+            epoch_path: dir.as_ref().join(format!("epochs.{}", extension)),
+            writers: None,
+            append: false,
+            log_every: 1,
+        })
+    }
+
+    pub fn csv(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(dir, LogFormat::Csv)
+    }
+
+    pub fn json_lines(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(dir, LogFormat::JsonLines)
+    }
+
+    // only every n-th batch of an epoch is logged
+    pub fn log_every(mut self, n: u32) -> Self {
+        assert!(n > 0);
+        self.log_every = n;
+        self
+    }
+
+    fn writers(&mut self) -> io::Result<&mut (BufWriter<File>, BufWriter<File>)> {
+        if self.writers.is_none() {
+            self.writers = Some((
+                open_log(&self.batch_path, self.format, &BATCH_COLUMNS, self.append)?,
+                open_log(&self.epoch_path, self.format, &EPOCH_COLUMNS, self.append)?,
+            ));
+        }
+        Ok(self.writers.as_mut().unwrap())
+    }
+
+    fn write_batch(&mut self, metrics: &BatchMetrics) -> io::Result<()> {
+        let values = [
+            field(metrics.epoch),
+            field(metrics.batch),
+            field(metrics.loss),
+            field(metrics.accuracy),
+            field(metrics.lr),
+            field(metrics.grad_norm),
+            field(metrics.samples_per_second),
+        ];
+        let format = self.format;
+        let (batch_writer, _) = self.writers()?;
+        write_row(batch_writer, format, &BATCH_COLUMNS, &values)
+    }
+
+    fn write_epoch(&mut self, metrics: &EpochMetrics) -> io::Result<()> {
+        let val = metrics.val.as_ref();
+        let values = [
+            field(metrics.epoch),
+            field(metrics.loss),
+            field(metrics.accuracy),
+            field(metrics.lr),
+            val.and_then(|val| field(val.loss)),
+            val.and_then(|val| field(val.accuracy)),
+            val.and_then(|val| field(val.top_k_accuracy)),
+            val.and_then(|val| field(val.macro_f1())),
+            field(metrics.samples_per_second),
+            field(metrics.seconds),
+        ];
+        let format = self.format;
+        let (batch_writer, epoch_writer) = self.writers()?;
+        write_row(epoch_writer, format, &EPOCH_COLUMNS, &values)?;
+
+        // flushed once per epoch so the files can be read while training runs
+        batch_writer.flush()?;
+        epoch_writer.flush()
+    }
+}
+
+// non-finite values are logged as missing
+fn field<T: Into<f64> + Display + Copy>(value: T) -> Option<String> {
+    if value.into().is_finite() {
+        Some(value.to_string())
+    } else {
+        None
+    }
+}
+
+// csv files get a header unless they are appended to
+fn open_log(
+    path: &Path,
+    format: LogFormat,
+    columns: &[&str],
+    append: bool,
+) -> io::Result<BufWriter<File>> {
+    let is_new = !append || fs::metadata(path).map_or(true, |metadata| metadata.len() == 0);
+    let file = if append {
+        OpenOptions::new().create(true).append(true).open(path)?
+    } else {
+        File::create(path)?
+    };
+    let mut writer = BufWriter::new(file);
+    if is_new && format == LogFormat::Csv {
+        writeln!(writer, "{}", columns.join(","))?;
+    }
+    Ok(writer)
+}
+
+fn write_row(
+    writer: &mut impl Write,
+    format: LogFormat,
+    columns: &[&str],
+    values: &[Option<String>],
+) -> io::Result<()> {
+    match format {
+        LogFormat::Csv => {
+            let fields: Vec<String> = values
+                .iter()
+                .map(|value| value.clone().unwrap_or_default())
+                .collect();
+            writeln!(writer, "{}", fields.join(","))
+        }
+        LogFormat::JsonLines => {
+            let fields: Vec<String> = columns
+                .iter()
+                .zip(values)
+                .map(|(column, value)| match value {
+                    Some(value) => format!("\"{}\":{}", column, value),
+                    None => format!("\"{}\":null", column),
+                })
+                .collect();
+            writeln!(writer, "{{{}}}", fields.join(","))
+        }
+    }
+}
+
+impl Callback for MetricsLogger {
+    fn on_batch_end(&mut self, _trainer: &Trainer, metrics: &BatchMetrics) {
+        if !metrics.batch.is_multiple_of(self.log_every) {
+            return;
+        }
+        if let Err(err) = self.write_batch(metrics) {
+            println!("Error writing batch metrics: {}", err);
+        }
+    }
+
+    fn on_epoch_end(&mut self, _trainer: &Trainer, metrics: &EpochMetrics) {
+        if let Err(err) = self.write_epoch(metrics) {
+            println!("Error writing epoch metrics: {}", err);
+        }
+    }
+
+    // only called when the trainer resumes from a checkpoint
+    fn load_state(&mut self, _state: &[u8]) -> io::Result<()> {
+        self.append = true;
+        self.writers = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    callback::{Callback, EpochMetrics, Monitor},
+    trainer::Trainer,
+};
+
+// after every epoch writes the trainer checkpoint to dir/LATEST_CHECKPOINT_FILE
+// and the network to dir/BEST_NETWORK_FILE whenever the monitored metric improves
+// the best value is part of the checkpoint, so resuming from it keeps the best network
+pub struct ModelCheckpoint {
+    dir: PathBuf,
+    monitor: Monitor,
+    best: Option<f32>,
+}
+
+impl ModelCheckpoint {
+    pub const LATEST_CHECKPOINT_FILE: &str = "latest-checkpoint.bin";
+    pub const BEST_NETWORK_FILE: &str = "best-network.bin";
+
+    pub fn new(dir: impl Into<PathBuf>, monitor: Monitor) -> Self {
+        Self {
+            dir: dir.into(),
+            monitor,
+            best: None,
+        }
+    }
+
+    pub fn latest_checkpoint_path(dir: impl AsRef<Path>) -> PathBuf {
+        dir.as_ref().join(Self::LATEST_CHECKPOINT_FILE)
+    }
+
+    pub fn best_network_path(dir: impl AsRef<Path>) -> PathBuf {
+        dir.as_ref().join(Self::BEST_NETWORK_FILE)
+    }
+
+    pub fn best(&self) -> Option<f32> {
+        self.best
+    }
+
+    fn save_best(&mut self, trainer: &Trainer, metrics: &EpochMetrics) -> io::Result<()> {
+        let value = self.monitor.value(metrics);
+        if self.monitor.improves(value, self.best, 0.0) {
+            let data = wincode::serialize(trainer.network())
+                .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+            fs::create_dir_all(&self.dir)?;
+            fs::write(Self::best_network_path(&self.dir), data)?;
+            self.best = Some(value);
+        }
+        Ok(())
+    }
+
+    fn print_error(&self, err: io::Error) {
+        println!(
+            "Error writing checkpoint to {}: {}",
+            self.dir.display(),
+            err
+        );
+    }
+}
+
+impl Callback for ModelCheckpoint {
+    fn on_epoch_end(&mut self, trainer: &Trainer, metrics: &EpochMetrics) {
+        if let Err(err) = self.save_best(trainer, metrics) {
+            self.print_error(err);
+        }
+    }
+
+    // the latest checkpoint is written after the other callbacks updated their state
+    fn after_epoch_end(&self, trainer: &Trainer) {
+        let result = fs::create_dir_all(&self.dir)
+            .and_then(|()| trainer.save_checkpoint(Self::latest_checkpoint_path(&self.dir)));
+        if let Err(err) = result {
+            self.print_error(err);
+        }
+    }
+
+    fn state(&self) -> Vec<u8> {
+        wincode::serialize(&self.best).expect("Could not serialize the best value")
+    }
+
+    fn check_state(&self, state: &[u8]) -> io::Result<()> {
+        wincode::deserialize::<Option<f32>>(state)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        Ok(())
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> io::Result<()> {
+        self.best = wincode::deserialize(state)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        Ok(())
+    }
+}
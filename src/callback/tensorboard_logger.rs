@@ -0,0 +1,156 @@
+use std::{io, path::Path};
+
+use crate::{
+    callback::{BatchMetrics, Callback, EpochMetrics},
+    layer::Layer,
+    tensorboard::SummaryWriter,
+    trainer::Trainer,
+};
+
+// logs batch and epoch scalars, per layer histograms of the params and their gradients
+// and the sample images once at the end of the first epoch
+pub struct TensorBoardLogger {
+    writer: SummaryWriter,
+    log_every: u32,
+    sample_images: Vec<Vec<f32>>,
+    image_width: u32,
+    image_height: u32,
+    images_logged: bool,
+}
+
+impl TensorBoardLogger {
+    pub fn new(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            writer: SummaryWriter::new(dir)?,
+            log_every: 1,
+            sample_images: Vec::new(),
+            image_width: 0,
+            image_height: 0,
+            images_logged: false,
+        })
+    }
+
+    // only every n-th batch of an epoch is logged
+    pub fn log_every(mut self, n: u32) -> Self {
+        assert!(n > 0);
+        self.log_every = n;
+        self
+    }
+
+    // inputs are width x height grayscale images with pixels in 0..=1
+    pub fn sample_images(mut self, inputs: Vec<Vec<f32>>, width: u32, height: u32) -> Self {
+        assert!(
+            inputs
+                .iter()
+                .all(|input| input.len() == (width * height) as usize)
+        );
+        self.sample_images = inputs;
+        self.image_width = width;
+        self.image_height = height;
+        self
+    }
+
+    fn write_batch(&mut self, metrics: &BatchMetrics) -> io::Result<()> {
+        let step = (metrics.epoch * metrics.num_batches + metrics.batch) as u64;
+        self.writer.add_scalar("batch/loss", metrics.loss, step)?;
+        self.writer
+            .add_scalar("batch/accuracy", metrics.accuracy, step)?;
+        self.writer.add_scalar("batch/lr", metrics.lr, step)?;
+        self.writer
+            .add_scalar("batch/grad_norm", metrics.grad_norm, step)?;
+        self.writer.add_scalar(
+            "batch/samples_per_second",
+            metrics.samples_per_second as f32,
+            step,
+        )
+    }
+
+    fn write_epoch(&mut self, trainer: &Trainer, metrics: &EpochMetrics) -> io::Result<()> {
+        let step = metrics.epoch as u64;
+        self.writer.add_scalar("epoch/loss", metrics.loss, step)?;
+        self.writer
+            .add_scalar("epoch/accuracy", metrics.accuracy, step)?;
+        self.writer.add_scalar("epoch/lr", metrics.lr, step)?;
+        self.writer.add_scalar(
+            "epoch/samples_per_second",
+            metrics.samples_per_second as f32,
+            step,
+        )?;
+        if let Some(val) = &metrics.val {
+            self.writer.add_scalar("val/loss", val.loss, step)?;
+            self.writer.add_scalar("val/accuracy", val.accuracy, step)?;
+            self.writer
+                .add_scalar("val/top_k_accuracy", val.top_k_accuracy, step)?;
+            self.writer
+                .add_scalar("val/macro_f1", val.macro_f1(), step)?;
+        }
+
+        // gradients are the ones of the last batch of the epoch, scaled like the logged grad_norm
+        let params = trainer.network().param_buffer();
+        let grad_scale = trainer.grad_scale();
+        let grads: Vec<f32> = trainer
+            .param_grads()
+            .iter()
+            .map(|grad| grad * grad_scale)
+            .collect();
+        for (idx, layer) in trainer.network().layers().iter().enumerate() {
+            let Layer::Dense(dense_layer) = layer else {
+                continue;
+            };
+            let weights = dense_layer.weight_range();
+            let biases = dense_layer.bias_range();
+            self.writer.add_histogram(
+                &format!("layer_{}/weights", idx),
+                &params[weights.clone()],
+                step,
+            )?;
+            self.writer.add_histogram(
+                &format!("layer_{}/biases", idx),
+                &params[biases.clone()],
+                step,
+            )?;
+            self.writer.add_histogram(
+                &format!("layer_{}/weight_grads", idx),
+                &grads[weights],
+                step,
+            )?;
+            self.writer.add_histogram(
+                &format!("layer_{}/bias_grads", idx),
+                &grads[biases],
+                step,
+            )?;
+        }
+
+        if !self.images_logged {
+            for (idx, image) in self.sample_images.iter().enumerate() {
+                self.writer.add_image(
+                    &format!("samples/{}", idx),
+                    image,
+                    self.image_width,
+                    self.image_height,
+                    step,
+                )?;
+            }
+            self.images_logged = true;
+        }
+
+        self.writer.flush()
+    }
+}
+
+impl Callback for TensorBoardLogger {
+    fn on_batch_end(&mut self, _trainer: &Trainer, metrics: &BatchMetrics) {
+        if !metrics.batch.is_multiple_of(self.log_every) {
+            return;
+        }
+        if let Err(err) = self.write_batch(metrics) {
+            println!("Error writing tensorboard batch summaries: {}", err);
+        }
+    }
+
+    fn on_epoch_end(&mut self, trainer: &Trainer, metrics: &EpochMetrics) {
+        if let Err(err) = self.write_epoch(trainer, metrics) {
+            println!("Error writing tensorboard epoch summaries: {}", err);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use crate::{
+    DataPoint,
+    eval::EvalReport,
+    max_index,
+    network::{Network, NetworkBuilder},
+    trainer::Trainer,
+};
+
+// indices of the samples of each class, the max_index of the target, in random order
+fn shuffled_classes(dataset: &[DataPoint], rng: &mut StdRng) -> Vec<Vec<usize>> {
+    let mut classes: Vec<Vec<usize>> = Vec::new();
+    for (idx, data_pt) in dataset.iter().enumerate() {
+        let class = max_index(&data_pt.target);
+        if class >= classes.len() {
+            classes.resize(class + 1, Vec::new());
+        }
+        classes[class].push(idx);
+    }
+    for samples in &mut classes {
+        samples.shuffle(rng);
+    }
+    classes
+}
+
+// splits off val_fraction of every class as the validation set, returns (train, val)
+pub fn stratified_split(
+    dataset: &[DataPoint],
+    val_fraction: f32,
+    rng: &mut StdRng,
+) -> (Vec<DataPoint>, Vec<DataPoint>) {
+    assert!((0.0..1.0).contains(&val_fraction));
+
+    let mut train = Vec::new();
+    let mut val = Vec::new();
+    for samples in shuffled_classes(dataset, rng) {
+        let num_val = (samples.len() as f32 * val_fraction).round() as usize;
+        val.extend(samples[..num_val].iter().map(|&idx| dataset[idx].clone()));
+        train.extend(samples[num_val..].iter().map(|&idx| dataset[idx].clone()));
+    }
+    (train, val)
+}
+
+// splits the indices of dataset into k folds with roughly equal class proportions
+pub fn stratified_k_fold(dataset: &[DataPoint], k: usize, rng: &mut StdRng) -> Vec<Vec<usize>> {
+    assert!(k >= 2 && k <= dataset.len());
+
+    let mut folds = vec![Vec::new(); k];
+    // dealing the classes one after another keeps the fold sizes within one sample
+    let mut fold = 0;
+    for samples in shuffled_classes(dataset, rng) {
+        for idx in samples {
+            folds[fold].push(idx);
+            fold = (fold + 1) % k;
+        }
+    }
+    folds
+}
+
+pub struct CrossValidationReport {
+    pub folds: Vec<EvalReport>,
+}
+
+impl CrossValidationReport {
+    // mean and standard deviation over the folds
+    fn stats(&self, metric: impl Fn(&EvalReport) -> f32) -> (f32, f32) {
+        let values: Vec<f32> = self.folds.iter().map(metric).collect();
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let variance = values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f32>()
+            / values.len() as f32;
+        (mean, variance.sqrt())
+    }
+
+    pub fn loss(&self) -> (f32, f32) {
+        self.stats(|report| report.loss)
+    }
+
+    pub fn accuracy(&self) -> (f32, f32) {
+        self.stats(|report| report.accuracy)
+    }
+
+    pub fn top_k_accuracy(&self) -> (f32, f32) {
+        self.stats(|report| report.top_k_accuracy)
+    }
+
+    pub fn macro_f1(&self) -> (f32, f32) {
+        self.stats(|report| report.macro_f1())
+    }
+
+    // sum of the confusion matrices of all folds
+    pub fn confusion_matrix(&self) -> Vec<Vec<u32>> {
+        let mut confusion_matrix = self.folds[0].confusion_matrix.clone();
+        for report in &self.folds[1..] {
+            for (row, fold_row) in confusion_matrix.iter_mut().zip(&report.confusion_matrix) {
+                for (count, fold_count) in row.iter_mut().zip(fold_row) {
+                    *count += fold_count;
+                }
+            }
+        }
+        confusion_matrix
+    }
+
+    pub fn print(&self) {
+        for (fold, report) in self.folds.iter().enumerate() {
+            println!(
+                "Fold {}: loss {}, accuracy {}, macro F1 {}",
+                fold,
+                report.loss,
+                report.accuracy,
+                report.macro_f1()
+            );
+        }
+
+        let (loss, loss_std) = self.loss();
+        let (accuracy, accuracy_std) = self.accuracy();
+        let (top_k_accuracy, top_k_accuracy_std) = self.top_k_accuracy();
+        let (macro_f1, macro_f1_std) = self.macro_f1();
+        println!("Loss: {} +- {}", loss, loss_std);
+        println!("Accuracy: {} +- {}", accuracy, accuracy_std);
+        println!(
+            "Top-{} accuracy: {} +- {}",
+            self.folds[0].top_k, top_k_accuracy, top_k_accuracy_std
+        );
+        println!("Macro F1: {} +- {}", macro_f1, macro_f1_std);
+    }
+}
+
+// trains a fresh network for every fold on the other k - 1 folds and evaluates it on the fold
+// network_factory returns the layout to train, trainer_factory configures training of it
+pub fn cross_validate<N, T>(
+    dataset: &[DataPoint],
+    k: usize,
+    epochs: u32,
+    seed: u64,
+    network_factory: N,
+    trainer_factory: T,
+) -> CrossValidationReport
+where
+    N: Fn() -> NetworkBuilder,
+    T: Fn(Network) -> Trainer,
+{
+    let folds = stratified_k_fold(dataset, k, &mut StdRng::seed_from_u64(seed));
+
+    let mut reports = Vec::with_capacity(k);
+    for (fold, val_indices) in folds.iter().enumerate() {
+        let val: Vec<DataPoint> = val_indices
+            .iter()
+            .map(|&idx| dataset[idx].clone())
+            .collect();
+        let train: Vec<DataPoint> = folds
+            .iter()
+            .enumerate()
+            .filter(|&(other, _)| other != fold)
+            .flat_map(|(_, indices)| indices.iter().map(|&idx| dataset[idx].clone()))
+            .collect();
+
+        println!("Training fold {} of {}", fold + 1, k);
+        let mut network = network_factory().build();
+        network.init_rand();
+        let mut trainer = trainer_factory(network);
+        if let Err(err) = trainer.fit(&train, Some(&val), epochs) {
+            println!("Fold {} stopped: {}", fold + 1, err);
+        }
+        reports.push(trainer.evaluate(&val));
+    }
+    CrossValidationReport { folds: reports }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10, 21 and 30 samples of classes 0, 1 and 2, the input holds the sample's index
+    fn dataset() -> Vec<DataPoint> {
+        [10, 21, 30]
+            .iter()
+            .enumerate()
+            .flat_map(|(class, &count)| (0..count).map(move |_| class))
+            .enumerate()
+            .map(|(idx, class)| {
+                let mut target = vec![0.0; 3];
+                target[class] = 1.0;
+                DataPoint {
+                    input: vec![idx as f32],
+                    target,
+                }
+            })
+            .collect()
+    }
+
+    fn class_counts(data_pts: impl Iterator<Item = usize>, dataset: &[DataPoint]) -> [usize; 3] {
+        let mut counts = [0; 3];
+        for idx in data_pts {
+            counts[max_index(&dataset[idx].target)] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn split_keeps_class_proportions() {
+        let dataset = dataset();
+        let (train, val) = stratified_split(&dataset, 0.2, &mut StdRng::seed_from_u64(0));
+
+        let indices = |split: &[DataPoint]| -> Vec<usize> {
+            split
+                .iter()
+                .map(|data_pt| data_pt.input[0] as usize)
+                .collect()
+        };
+        assert!(class_counts(indices(&val).into_iter(), &dataset) == [2, 4, 6]);
+        assert!(class_counts(indices(&train).into_iter(), &dataset) == [8, 17, 24]);
+
+        let mut all = [indices(&train), indices(&val)].concat();
+        all.sort();
+        assert!(all == (0..dataset.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn folds_are_disjoint_and_balanced() {
+        let dataset = dataset();
+        let folds = stratified_k_fold(&dataset, 4, &mut StdRng::seed_from_u64(0));
+        assert!(folds.len() == 4);
+
+        let sizes: Vec<usize> = folds.iter().map(|fold| fold.len()).collect();
+        assert!(sizes.iter().max().unwrap() - sizes.iter().min().unwrap() <= 1);
+        for class in 0..3 {
+            let counts: Vec<usize> = folds
+                .iter()
+                .map(|fold| class_counts(fold.iter().copied(), &dataset)[class])
+                .collect();
+            assert!(counts.iter().max().unwrap() - counts.iter().min().unwrap() <= 1);
+        }
+
+        let mut all = folds.concat();
+        all.sort();
+        assert!(all == (0..dataset.len()).collect::<Vec<_>>());
+    }
+}
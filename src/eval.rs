@@ -0,0 +1,300 @@
+use std::{fs, io, path::Path};
+
+use crate::{DataPoint, loss::Loss, max_index, network::Network};
+
+pub struct ClassMetrics {
+    pub precision: f32,
+    pub recall: f32,
+    pub f1: f32,
+    // number of samples whose target is this class
+    pub support: u32,
+}
+
+pub struct EvalReport {
+    pub num_samples: u32,
+    // mean loss per sample
+    pub loss: f32,
+    pub accuracy: f32,
+    pub top_k: u32,
+    pub top_k_accuracy: f32,
+    pub classes: Vec<ClassMetrics>,
+    // confusion_matrix[target][prediction]
+    pub confusion_matrix: Vec<Vec<u32>>,
+}
+
+// runs the network on dataset in batches of batch_size
+// classes are the max_index of the targets and outputs
+// an empty dataset gives a report with NaN loss and accuracies
+pub fn evaluate(
+    network: &Network,
+    loss_fn: &dyn Loss,
+    dataset: &[DataPoint],
+    batch_size: u32,
+    top_k: u32,
+) -> EvalReport {
+    assert!(top_k > 0);
+
+    let num_classes = network.layers().last().unwrap().output_size() as usize;
+    let mut confusion_matrix = vec![vec![0; num_classes]; num_classes];
+    let mut total_loss = 0.0;
+    let mut total_top_k = 0;
+
+    for batch in dataset.chunks(batch_size as usize) {
+        let inputs: Vec<f32> = batch
+            .iter()
+            .flat_map(|data_pt| data_pt.input.iter().copied())
+            .collect();
+        let outputs = network.forward_batch(&inputs, batch.len() as u32);
+
+        for (output, data_pt) in outputs.chunks(num_classes).zip(batch) {
+            total_loss += loss_fn.forward(output, &data_pt.target);
+
+            let target = max_index(&data_pt.target);
+            confusion_matrix[target][max_index(output)] += 1;
+
+            // the target is in the top k if fewer than k outputs beat it
+            let num_higher = output
+                .iter()
+                .filter(|&&value| value > output[target])
+                .count();
+            if num_higher < top_k as usize {
+                total_top_k += 1;
+            }
+        }
+    }
+
+    let classes = (0..num_classes)
+        .map(|class| {
+            let true_positives = confusion_matrix[class][class];
+            let support: u32 = confusion_matrix[class].iter().sum();
+            let predicted: u32 = confusion_matrix.iter().map(|row| row[class]).sum();
+
+            let precision = ratio(true_positives, predicted);
+            let recall = ratio(true_positives, support);
+            let f1 = if precision + recall > 0.0 {
+                2.0 * precision * recall / (precision + recall)
+            } else {
+                0.0
+            };
+            ClassMetrics {
+                precision,
+                recall,
+                f1,
+                support,
+            }
+        })
+        .collect();
+
+    let num_samples = dataset.len() as u32;
+    let num_correct: u32 = (0..num_classes)
+        .map(|class| confusion_matrix[class][class])
+        .sum();
+    EvalReport {
+        num_samples,
+        loss: total_loss / num_samples as f32,
+        accuracy: num_correct as f32 / num_samples as f32,
+        top_k,
+        top_k_accuracy: total_top_k as f32 / num_samples as f32,
+        classes,
+        confusion_matrix,
+    }
+}
+
+fn ratio(count: u32, total: u32) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f32 / total as f32
+    }
+}
+
+impl EvalReport {
+    // unweighted mean over the classes
+    pub fn macro_f1(&self) -> f32 {
+        self.classes.iter().map(|class| class.f1).sum::<f32>() / self.classes.len() as f32
+    }
+
+    pub fn print(&self) {
+        println!("Samples: {}", self.num_samples);
+        println!("Loss: {}", self.loss);
+        println!("Accuracy: {}", self.accuracy);
+        println!("Top-{} accuracy: {}", self.top_k, self.top_k_accuracy);
+        println!("Macro F1: {}", self.macro_f1());
+
+        println!();
+        println!(
+            "{:>6} {:>10} {:>10} {:>10} {:>8}",
+            "class", "precision", "recall", "f1", "support"
+        );
+        for (class, metrics) in self.classes.iter().enumerate() {
+            println!(
+                "{:>6} {:>10.4} {:>10.4} {:>10.4} {:>8}",
+                class, metrics.precision, metrics.recall, metrics.f1, metrics.support
+            );
+        }
+
+        println!();
+        println!("Confusion matrix (rows: target, columns: prediction)");
+        print!("{:>6}", "");
+        for class in 0..self.confusion_matrix.len() {
+            print!(" {:>6}", class);
+        }
+        println!();
+        for (class, row) in self.confusion_matrix.iter().enumerate() {
+            print!("{:>6}", class);
+            for count in row {
+                print!(" {:>6}", count);
+            }
+            println!();
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let classes: Vec<String> = self
+            .classes
+            .iter()
+            .map(|class| {
+                format!(
+                    "{{\"precision\":{},\"recall\":{},\"f1\":{},\"support\":{}}}",
+                    json_number(class.precision),
+                    json_number(class.recall),
+                    json_number(class.f1),
+                    class.support
+                )
+            })
+            .collect();
+        let confusion_matrix: Vec<String> = self
+            .confusion_matrix
+            .iter()
+            .map(|row| {
+                let counts: Vec<String> = row.iter().map(|count| count.to_string()).collect();
+                format!("[{}]", counts.join(","))
+            })
+            .collect();
+
+        format!(
+            "{{\"num_samples\":{},\"loss\":{},\"accuracy\":{},\"top_k\":{},\"top_k_accuracy\":{},\
+             \"classes\":[{}],\"confusion_matrix\":[{}]}}",
+            self.num_samples,
+            json_number(self.loss),
+            json_number(self.accuracy),
+            self.top_k,
+            json_number(self.top_k_accuracy),
+            classes.join(","),
+            confusion_matrix.join(",")
+        )
+    }
+
+    // one row per target class with its metrics followed by its confusion matrix row
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("class,precision,recall,f1,support");
+        for class in 0..self.classes.len() {
+            csv += &format!(",predicted_{}", class);
+        }
+        csv += "\n";
+
+        for (class, (metrics, row)) in self
+            .classes
+            .iter()
+            .zip(self.confusion_matrix.iter())
+            .enumerate()
+        {
+            csv += &format!(
+                "{},{},{},{},{}",
+                class, metrics.precision, metrics.recall, metrics.f1, metrics.support
+            );
+            for count in row {
+                csv += &format!(",{}", count);
+            }
+            csv += "\n";
+        }
+        csv
+    }
+
+    pub fn save_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+}
+
+// json has no NaN or infinity
+fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{loss::Mse, network::NetworkBuilder};
+
+    // (target, prediction) pairs, the identity network predicts the class of a one-hot input
+    const SAMPLES: [(usize, usize); 6] = [(0, 0), (0, 0), (0, 1), (1, 1), (2, 1), (2, 2)];
+
+    fn one_hot(class: usize) -> Vec<f32> {
+        let mut values = vec![0.0; 3];
+        values[class] = 1.0;
+        values
+    }
+
+    fn identity_network() -> Network {
+        let mut network = NetworkBuilder::new(3).add_dense_layer(3).build();
+        let params = network.param_buffer_mut();
+        params.fill(0.0);
+        for class in 0..3 {
+            params[class * 3 + class] = 1.0;
+        }
+        network
+    }
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!(
+            (value - expected).abs() < 1e-6,
+            "{} is not {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn counts_known_predictions() {
+        let dataset: Vec<DataPoint> = SAMPLES
+            .iter()
+            .map(|&(target, prediction)| DataPoint {
+                input: one_hot(prediction),
+                target: one_hot(target),
+            })
+            .collect();
+
+        let report = evaluate(&identity_network(), &Mse::new(), &dataset, 4, 2);
+        assert!(report.num_samples == 6);
+        assert!(report.confusion_matrix == vec![vec![2, 1, 0], vec![0, 1, 0], vec![0, 1, 1]]);
+        assert_close(report.accuracy, 4.0 / 6.0);
+        // a wrong prediction leaves the target second, so it is always in the top 2
+        assert_close(report.top_k_accuracy, 1.0);
+
+        let expected = [(1.0, 2.0 / 3.0, 3), (1.0 / 3.0, 1.0, 1), (1.0, 0.5, 2)];
+        for (metrics, (precision, recall, support)) in report.classes.iter().zip(expected) {
+            assert_close(metrics.precision, precision);
+            assert_close(metrics.recall, recall);
+            assert_close(metrics.f1, 2.0 * precision * recall / (precision + recall));
+            assert!(metrics.support == support);
+        }
+    }
+
+    #[test]
+    fn empty_dataset_gives_nan_metrics() {
+        let report = evaluate(&identity_network(), &Mse::new(), &[], 4, 1);
+        assert!(report.num_samples == 0);
+        assert!(report.loss.is_nan());
+        assert!(report.accuracy.is_nan());
+        assert!(report.top_k_accuracy.is_nan());
+        assert!(report.classes.iter().all(|class| class.support == 0));
+    }
+}
@@ -0,0 +1,387 @@
+use crate::{DataPoint, layer::Layer, trainer::Trainer};
+
+pub struct LayerGradError {
+    pub layer: usize,
+    pub max_rel_error: f32,
+}
+
+pub struct GradCheckReport {
+    // only layers with trainable params are checked
+    pub layers: Vec<LayerGradError>,
+    pub input_max_rel_error: f32,
+}
+
+impl GradCheckReport {
+    pub fn max_rel_error(&self) -> f32 {
+        self.layers
+            .iter()
+            .map(|layer| layer.max_rel_error)
+            .fold(self.input_max_rel_error, f32::max)
+    }
+
+    pub fn print(&self) {
+        for layer in &self.layers {
+            println!(
+                "Layer {} max relative error: {}",
+                layer.layer, layer.max_rel_error
+            );
+        }
+        println!("Input max relative error: {}", self.input_max_rel_error);
+    }
+}
+
+fn rel_error(analytic: f32, numeric: f32) -> f32 {
+    // the floor keeps float noise on tiny gradients from showing up as huge relative errors
+    (analytic - numeric).abs() / (analytic.abs() + numeric.abs()).max(1e-3)
+}
+
+// compares the trainer's backward pass against central finite differences of the summed batch loss
+// the network is left unchanged, batch.len() must equal the trainer's batch size
+pub fn check_gradients(
+    trainer: &mut Trainer,
+    batch: &[DataPoint],
+    epsilon: f32,
+) -> GradCheckReport {
+    // batch_loss only has the hard target loss, the gradients would never match
+    assert!(
+        !trainer.is_distilling(),
+        "Please check gradients on a trainer without distillation"
+    );
+
+    trainer.compute_gradients(batch);
+    let param_grads = trainer.param_grads().to_vec();
+    let input_grads = trainer.input_grads().to_vec();
+
+    let mut layers = Vec::new();
+    for (idx, layer) in trainer.network().layers().to_vec().iter().enumerate() {
+        let Layer::Dense(dense_layer) = layer else {
+            continue;
+        };
+        if !trainer.is_trainable(idx) {
+            continue;
+        }
+
+        let mut max_rel_error = 0.0f32;
+        for param_idx in dense_layer.param_buffer_range() {
+            let numeric = numeric_grad(trainer, batch, epsilon, |trainer, offset| {
+                trainer.network_mut().param_buffer_mut()[param_idx] += offset;
+            });
+            max_rel_error = max_rel_error.max(rel_error(param_grads[param_idx], numeric));
+        }
+        layers.push(LayerGradError {
+            layer: idx,
+            max_rel_error,
+        });
+    }
+
+    let mut perturbed = batch.to_vec();
+    let mut input_max_rel_error = 0.0f32;
+    for sample in 0..batch.len() {
+        let input_size = batch[sample].input.len();
+        for i in 0..input_size {
+            let original = perturbed[sample].input[i];
+
+            perturbed[sample].input[i] = original + epsilon;
+            let loss_plus = trainer.batch_loss(&perturbed);
+            perturbed[sample].input[i] = original - epsilon;
+            let loss_minus = trainer.batch_loss(&perturbed);
+            perturbed[sample].input[i] = original;
+
+            let numeric = (loss_plus - loss_minus) / (2.0 * epsilon);
+            let analytic = input_grads[sample * input_size + i];
+            input_max_rel_error = input_max_rel_error.max(rel_error(analytic, numeric));
+        }
+    }
+
+    GradCheckReport {
+        layers,
+        input_max_rel_error,
+    }
+}
+
+// perturb adds the given offset to the value being checked
+fn numeric_grad<F>(trainer: &mut Trainer, batch: &[DataPoint], epsilon: f32, perturb: F) -> f32
+where
+    F: Fn(&mut Trainer, f32),
+{
+    perturb(trainer, epsilon);
+    let loss_plus = trainer.batch_loss(batch);
+    perturb(trainer, -2.0 * epsilon);
+    let loss_minus = trainer.batch_loss(batch);
+    perturb(trainer, epsilon);
+
+    (loss_plus - loss_minus) / (2.0 * epsilon)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::{
+        network::{Network, NetworkBuilder},
+        trainer::TrainerBuilder,
+    };
+
+    const EPSILON: f32 = 1e-2;
+    const TOLERANCE: f32 = 2e-2;
+
+    // seeded instead of init_rand so every run checks the same point, away from relu kinks
+    fn init_params(rng: &mut StdRng, network: &mut Network) {
+        for param in network.param_buffer_mut() {
+            *param = rng.random_range(-0.8..=0.8);
+        }
+    }
+
+    fn random_batch(rng: &mut StdRng, batch_size: usize, input_size: usize) -> Vec<DataPoint> {
+        (0..batch_size)
+            .map(|_| DataPoint {
+                input: (0..input_size)
+                    .map(|_| rng.random_range(-1.0..=1.0))
+                    .collect(),
+                target: Vec::new(),
+            })
+            .collect()
+    }
+
+    fn one_hot_targets(rng: &mut StdRng, batch: &mut [DataPoint], num_classes: usize) {
+        for data_pt in batch {
+            data_pt.target = vec![0.0; num_classes];
+            data_pt.target[rng.random_range(0..num_classes)] = 1.0;
+        }
+    }
+
+    fn regression_targets(rng: &mut StdRng, batch: &mut [DataPoint], output_size: usize) {
+        for data_pt in batch {
+            data_pt.target = (0..output_size)
+                .map(|_| rng.random_range(-1.0..=1.0))
+                .collect();
+        }
+    }
+
+    #[test]
+    fn dense_mse() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut network = NetworkBuilder::new(5).add_dense_layer(3).build();
+        init_params(&mut rng, &mut network);
+        let mut batch = random_batch(&mut rng, 4, 5);
+        regression_targets(&mut rng, &mut batch, 3);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(4)
+            .mse()
+            .sgd(0.1)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.max_rel_error() < TOLERANCE);
+    }
+
+    #[test]
+    fn dense_relu_mse() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut network = NetworkBuilder::new(6)
+            .add_dense_layer(8)
+            .add_relu()
+            .add_dense_layer(2)
+            .build();
+        init_params(&mut rng, &mut network);
+        let mut batch = random_batch(&mut rng, 3, 6);
+        regression_targets(&mut rng, &mut batch, 2);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(3)
+            .mse()
+            .sgd(0.1)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.layers.len() == 2);
+        assert!(report.max_rel_error() < TOLERANCE);
+    }
+
+    #[test]
+    fn relu_first_layer_input_grads() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut network = NetworkBuilder::new(4).add_relu().add_dense_layer(3).build();
+        init_params(&mut rng, &mut network);
+        let mut batch = random_batch(&mut rng, 2, 4);
+        regression_targets(&mut rng, &mut batch, 3);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(2)
+            .mse()
+            .sgd(0.1)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.input_max_rel_error < TOLERANCE);
+    }
+
+    #[test]
+    fn dense_relu_cross_entropy() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut network = NetworkBuilder::new(6)
+            .add_dense_layer(8)
+            .add_relu()
+            .add_dense_layer(4)
+            .build();
+        init_params(&mut rng, &mut network);
+        let mut batch = random_batch(&mut rng, 3, 6);
+        one_hot_targets(&mut rng, &mut batch, 4);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(3)
+            .cross_entropy()
+            .sgd(0.1)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.max_rel_error() < TOLERANCE);
+    }
+
+    #[test]
+    fn cross_entropy_smoothing_and_class_weights() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut network = NetworkBuilder::new(5).add_dense_layer(4).build();
+        init_params(&mut rng, &mut network);
+        let mut batch = random_batch(&mut rng, 3, 5);
+        one_hot_targets(&mut rng, &mut batch, 4);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(3)
+            .cross_entropy_with(0.1, Some(vec![1.0, 2.0, 0.5, 1.5]))
+            .sgd(0.1)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.max_rel_error() < TOLERANCE);
+    }
+
+    #[test]
+    fn frozen_layers_are_skipped() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut network = NetworkBuilder::new(4)
+            .add_dense_layer(5)
+            .add_relu()
+            .add_dense_layer(3)
+            .build();
+        init_params(&mut rng, &mut network);
+        let mut batch = random_batch(&mut rng, 2, 4);
+        regression_targets(&mut rng, &mut batch, 3);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(2)
+            .mse()
+            .sgd(0.1)
+            .freeze_layer(0)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.layers.len() == 1);
+        assert!(report.layers[0].layer == 2);
+        assert!(report.max_rel_error() < TOLERANCE);
+    }
+
+    fn binary_targets(rng: &mut StdRng, batch: &mut [DataPoint], output_size: usize) {
+        for data_pt in batch {
+            data_pt.target = (0..output_size)
+                .map(|_| if rng.random_bool(0.5) { 1.0 } else { 0.0 })
+                .collect();
+        }
+    }
+
+    fn probability_targets(rng: &mut StdRng, batch: &mut [DataPoint], output_size: usize) {
+        for data_pt in batch {
+            let weights: Vec<f32> = (0..output_size)
+                .map(|_| rng.random_range(0.1..=1.0))
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            data_pt.target = weights.iter().map(|weight| weight / sum).collect();
+        }
+    }
+
+    fn dense_relu_network(rng: &mut StdRng, input_size: u32, output_size: u32) -> Network {
+        let mut network = NetworkBuilder::new(input_size)
+            .add_dense_layer(6)
+            .add_relu()
+            .add_dense_layer(output_size)
+            .build();
+        init_params(rng, &mut network);
+        network
+    }
+
+    #[test]
+    fn focal() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let network = dense_relu_network(&mut rng, 5, 4);
+        let mut batch = random_batch(&mut rng, 3, 5);
+        one_hot_targets(&mut rng, &mut batch, 4);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(3)
+            .focal(2.0, Some(vec![0.25, 0.5, 1.0, 0.75]))
+            .sgd(0.1)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.max_rel_error() < TOLERANCE);
+    }
+
+    #[test]
+    fn binary_cross_entropy() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let network = dense_relu_network(&mut rng, 5, 3);
+        let mut batch = random_batch(&mut rng, 3, 5);
+        binary_targets(&mut rng, &mut batch, 3);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(3)
+            .bce_with_pos_weights(vec![1.0, 2.0, 0.5])
+            .sgd(0.1)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.max_rel_error() < TOLERANCE);
+    }
+
+    #[test]
+    fn kl_divergence() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let network = dense_relu_network(&mut rng, 5, 4);
+        let mut batch = random_batch(&mut rng, 3, 5);
+        probability_targets(&mut rng, &mut batch, 4);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(3)
+            .kl_divergence(2.0)
+            .sgd(0.1)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.max_rel_error() < TOLERANCE);
+    }
+
+    #[test]
+    fn sigmoid_mse() {
+        let mut rng = StdRng::seed_from_u64(10);
+        let network = dense_relu_network(&mut rng, 5, 3);
+        let mut batch = random_batch(&mut rng, 3, 5);
+        probability_targets(&mut rng, &mut batch, 3);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(3)
+            .sigmoid_mse(1.5)
+            .sgd(0.1)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.max_rel_error() < TOLERANCE);
+    }
+
+    #[test]
+    fn huber() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let network = dense_relu_network(&mut rng, 5, 3);
+        let mut batch = random_batch(&mut rng, 3, 5);
+        regression_targets(&mut rng, &mut batch, 3);
+
+        let mut trainer = TrainerBuilder::new(network)
+            .batch_size(3)
+            .huber(0.5)
+            .sgd(0.1)
+            .build();
+        let report = check_gradients(&mut trainer, &batch, EPSILON);
+        assert!(report.max_rel_error() < TOLERANCE);
+    }
+}
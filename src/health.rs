@@ -0,0 +1,87 @@
+use std::{error::Error, fmt};
+
+#[derive(Clone, Copy, Debug)]
+pub enum NonFiniteSource {
+    Inputs,
+    // output of the layer
+    Activations(usize),
+    Loss,
+    // gradients of the layer's params
+    Gradients(usize),
+}
+
+// a training step produced a NaN or infinite value, the step's update was not applied
+#[derive(Clone, Copy, Debug)]
+pub struct NonFiniteError {
+    pub epoch: u32,
+    pub batch: u32,
+    pub source: NonFiniteSource,
+}
+
+impl fmt::Display for NonFiniteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.source {
+            NonFiniteSource::Inputs => write!(f, "Non-finite input")?,
+            NonFiniteSource::Activations(layer) => {
+                write!(f, "Non-finite activations in the output of layer {}", layer)?
+            }
+            NonFiniteSource::Loss => write!(f, "Non-finite loss")?,
+            NonFiniteSource::Gradients(layer) => {
+                write!(f, "Non-finite gradients of the params of layer {}", layer)?
+            }
+        }
+        write!(f, " at epoch {}, batch {}", self.epoch, self.batch)
+    }
+}
+
+impl Error for NonFiniteError {}
+
+pub struct DenseHealth {
+    pub layer: usize,
+    pub weight_norm: f32,
+    // norm of the mean per-sample gradient of the weights
+    pub grad_norm: f32,
+}
+
+impl DenseHealth {
+    // roughly how fast the layer's weights change relative to their size
+    pub fn grad_weight_ratio(&self) -> f32 {
+        self.grad_norm / self.weight_norm.max(f32::MIN_POSITIVE)
+    }
+}
+
+pub struct ReluHealth {
+    pub layer: usize,
+    // fraction of units that output zero for every sample of the batch
+    pub dead_fraction: f32,
+}
+
+// per layer statistics of the last training batch
+pub struct HealthReport {
+    pub dense: Vec<DenseHealth>,
+    pub relu: Vec<ReluHealth>,
+}
+
+impl HealthReport {
+    pub fn print(&self) {
+        for dense in &self.dense {
+            println!(
+                "Layer {} (dense): weight norm {}, grad norm {}, grad/weight ratio {}",
+                dense.layer,
+                dense.weight_norm,
+                dense.grad_norm,
+                dense.grad_weight_ratio()
+            );
+        }
+        for relu in &self.relu {
+            println!(
+                "Layer {} (relu): dead fraction {}",
+                relu.layer, relu.dead_fraction
+            );
+        }
+    }
+}
+
+pub fn norm(values: &[f32]) -> f32 {
+    values.iter().map(|value| value * value).sum::<f32>().sqrt()
+}
@@ -0,0 +1,454 @@
+use std::{
+    cmp::Ordering,
+    fs, io,
+    path::Path,
+    sync::{
+        Mutex,
+        atomic::{self, AtomicUsize},
+    },
+    thread,
+};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{DataPoint, callback::Monitor, eval::EvalReport, trainer::Trainer};
+
+pub enum ParamRange {
+    Choice(Vec<f32>),
+    Uniform(f32, f32),
+    // uniform in log space, for learning rates and weight decay
+    LogUniform(f32, f32),
+}
+
+pub struct SearchSpace {
+    params: Vec<(String, ParamRange)>,
+}
+
+impl SearchSpace {
+    pub fn new() -> Self {
+        Self { params: Vec::new() }
+    }
+
+    pub fn choice(mut self, name: &str, values: Vec<f32>) -> Self {
+        assert!(!values.is_empty());
+        self.params
+            .push((name.to_string(), ParamRange::Choice(values)));
+        self
+    }
+
+    pub fn uniform(mut self, name: &str, low: f32, high: f32) -> Self {
+        assert!(low <= high);
+        self.params
+            .push((name.to_string(), ParamRange::Uniform(low, high)));
+        self
+    }
+
+    pub fn log_uniform(mut self, name: &str, low: f32, high: f32) -> Self {
+        assert!(0.0 < low && low <= high);
+        self.params
+            .push((name.to_string(), ParamRange::LogUniform(low, high)));
+        self
+    }
+
+    pub fn sample(&self, rng: &mut StdRng) -> Params {
+        let values = self
+            .params
+            .iter()
+            .map(|(name, range)| {
+                let value = match range {
+                    ParamRange::Choice(values) => values[rng.random_range(0..values.len())],
+                    ParamRange::Uniform(low, high) => rng.random_range(*low..=*high),
+                    ParamRange::LogUniform(low, high) => {
+                        rng.random_range(low.ln()..=high.ln()).exp()
+                    }
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        Params { values }
+    }
+
+    // every combination of the choices, continuous ranges can't be put on a grid
+    pub fn grid(&self) -> Vec<Params> {
+        let mut grid = vec![Params { values: Vec::new() }];
+        for (name, range) in &self.params {
+            let ParamRange::Choice(choices) = range else {
+                panic!(
+                    "Parameter {} needs a list of choices for a grid search",
+                    name
+                );
+            };
+            grid = grid
+                .iter()
+                .flat_map(|params| {
+                    choices.iter().map(move |&value| {
+                        let mut params = params.clone();
+                        params.values.push((name.clone(), value));
+                        params
+                    })
+                })
+                .collect();
+        }
+        grid
+    }
+}
+
+#[derive(Clone)]
+pub struct Params {
+    values: Vec<(String, f32)>,
+}
+
+impl Params {
+    pub fn get(&self, name: &str) -> f32 {
+        self.values
+            .iter()
+            .find(|(param, _)| param == name)
+            .unwrap_or_else(|| panic!("Search space has no parameter {}", name))
+            .1
+    }
+
+    pub fn values(&self) -> &[(String, f32)] {
+        &self.values
+    }
+}
+
+pub struct TrialResult {
+    pub trial: usize,
+    pub params: Params,
+    // total number of epochs the trial has been trained for
+    pub epochs: u32,
+    pub score: f32,
+    pub report: EvalReport,
+}
+
+pub struct SearchResults {
+    pub monitor: Monitor,
+    // one row per trial and budget, in the order they finished training
+    pub results: Vec<TrialResult>,
+}
+
+impl SearchResults {
+    // the best score among the trials trained for the largest budget
+    pub fn best(&self) -> &TrialResult {
+        let max_epochs = self
+            .results
+            .iter()
+            .map(|result| result.epochs)
+            .max()
+            .unwrap();
+        self.results
+            .iter()
+            .filter(|result| result.epochs == max_epochs)
+            .min_by(|a, b| compare_scores(self.monitor, a.score, b.score))
+            .unwrap()
+    }
+
+    pub fn print(&self) {
+        let mut results: Vec<&TrialResult> = self.results.iter().collect();
+        results.sort_by(|a, b| {
+            b.epochs
+                .cmp(&a.epochs)
+                .then(compare_scores(self.monitor, a.score, b.score))
+        });
+
+        print!("{:>6} {:>7}", "trial", "epochs");
+        for (name, _) in self.results[0].params.values() {
+            print!(" {:>12}", name);
+        }
+        println!(" {:>10} {:>10} {:>10}", "score", "val_loss", "val_acc");
+        for result in results {
+            print!("{:>6} {:>7}", result.trial, result.epochs);
+            for (_, value) in result.params.values() {
+                print!(" {:>12.6}", value);
+            }
+            println!(
+                " {:>10.4} {:>10.4} {:>10.4}",
+                result.score, result.report.loss, result.report.accuracy
+            );
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("trial,epochs");
+        for (name, _) in self.results[0].params.values() {
+            csv += &format!(",{}", name);
+        }
+        csv += ",score,val_loss,val_accuracy,val_top_k_accuracy,val_macro_f1\n";
+
+        for result in &self.results {
+            csv += &format!("{},{}", result.trial, result.epochs);
+            for (_, value) in result.params.values() {
+                csv += &format!(",{}", value);
+            }
+            csv += &format!(
+                ",{},{},{},{},{}\n",
+                result.score,
+                result.report.loss,
+                result.report.accuracy,
+                result.report.top_k_accuracy,
+                result.report.macro_f1()
+            );
+        }
+        csv
+    }
+
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+}
+
+// Less means a is the better score
+fn compare_scores(monitor: Monitor, a: f32, b: f32) -> Ordering {
+    // NaN scores of diverged trials rank below every other score
+    let key = |score: f32| {
+        if score.is_nan() {
+            f32::INFINITY
+        } else if monitor.lower_is_better() {
+            score
+        } else {
+            -score
+        }
+    };
+    key(a).total_cmp(&key(b))
+}
+
+struct Trial {
+    id: usize,
+    params: Params,
+    // trainer state after the previous budget, for successive halving
+    checkpoint: Option<Vec<u8>>,
+}
+
+// trains one Trainer per trial on train and scores it with monitor on val
+// trials run in parallel, each is built and trained inside its worker thread
+pub struct HyperSearch<'a> {
+    train: &'a [DataPoint],
+    val: &'a [DataPoint],
+    monitor: Monitor,
+    num_threads: usize,
+    seed: u64,
+}
+
+impl<'a> HyperSearch<'a> {
+    pub fn new(train: &'a [DataPoint], val: &'a [DataPoint]) -> Self {
+        Self {
+            train,
+            val,
+            monitor: Monitor::ValAccuracy,
+            num_threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            seed: 0,
+        }
+    }
+
+    pub fn monitor(mut self, monitor: Monitor) -> Self {
+        assert!(
+            monitor != Monitor::TrainLoss,
+            "Please monitor a validation metric for a search"
+        );
+        self.monitor = monitor;
+        self
+    }
+
+    pub fn num_threads(mut self, num_threads: usize) -> Self {
+        assert!(num_threads > 0);
+        self.num_threads = num_threads;
+        self
+    }
+
+    // seeds the sampling of the search space
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    // build_trainer is given the params of a trial and returns a trainer with a fresh network
+    pub fn random<F>(
+        &self,
+        space: &SearchSpace,
+        num_trials: usize,
+        epochs: u32,
+        build_trainer: F,
+    ) -> SearchResults
+    where
+        F: Fn(&Params) -> Trainer + Sync,
+    {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let params = (0..num_trials).map(|_| space.sample(&mut rng)).collect();
+        self.run(params, epochs, build_trainer)
+    }
+
+    pub fn grid<F>(&self, space: &SearchSpace, epochs: u32, build_trainer: F) -> SearchResults
+    where
+        F: Fn(&Params) -> Trainer + Sync,
+    {
+        self.run(space.grid(), epochs, build_trainer)
+    }
+
+    // trains num_trials random trials for min_epochs, then keeps training the best 1 / eta
+    // of them for eta times as many epochs until one trial is left
+    pub fn successive_halving<F>(
+        &self,
+        space: &SearchSpace,
+        num_trials: usize,
+        min_epochs: u32,
+        eta: u32,
+        build_trainer: F,
+    ) -> SearchResults
+    where
+        F: Fn(&Params) -> Trainer + Sync,
+    {
+        assert!(num_trials > 0);
+        assert!(min_epochs > 0);
+        assert!(eta >= 2);
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut trials: Vec<Trial> = (0..num_trials)
+            .map(|id| Trial {
+                id,
+                params: space.sample(&mut rng),
+                checkpoint: None,
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        let mut epochs = min_epochs;
+        loop {
+            println!("Training {} trials for {} epochs", trials.len(), epochs);
+            let is_last_round = trials.len() == 1;
+            let round = self.run_trials(&trials, epochs, !is_last_round, &build_trainer);
+
+            let mut survivors: Vec<(TrialResult, Option<Vec<u8>>)> = round
+                .into_iter()
+                .zip(trials)
+                .map(|((report, checkpoint), trial)| {
+                    let result = TrialResult {
+                        trial: trial.id,
+                        params: trial.params,
+                        epochs,
+                        score: self.monitor.report_value(&report),
+                        report,
+                    };
+                    (result, checkpoint)
+                })
+                .collect();
+            if is_last_round {
+                results.extend(survivors.into_iter().map(|(result, _)| result));
+                break;
+            }
+
+            survivors.sort_by(|(a, _), (b, _)| compare_scores(self.monitor, a.score, b.score));
+            let num_survivors = survivors.len().div_ceil(eta as usize);
+            trials = survivors
+                .iter()
+                .take(num_survivors)
+                .map(|(result, checkpoint)| Trial {
+                    id: result.trial,
+                    params: result.params.clone(),
+                    checkpoint: checkpoint.clone(),
+                })
+                .collect();
+            results.extend(survivors.into_iter().map(|(result, _)| result));
+            epochs *= eta;
+        }
+
+        SearchResults {
+            monitor: self.monitor,
+            results,
+        }
+    }
+
+    fn run<F>(&self, params: Vec<Params>, epochs: u32, build_trainer: F) -> SearchResults
+    where
+        F: Fn(&Params) -> Trainer + Sync,
+    {
+        assert!(!params.is_empty());
+
+        let trials: Vec<Trial> = params
+            .into_iter()
+            .enumerate()
+            .map(|(id, params)| Trial {
+                id,
+                params,
+                checkpoint: None,
+            })
+            .collect();
+        let reports = self.run_trials(&trials, epochs, false, &build_trainer);
+
+        let results = reports
+            .into_iter()
+            .zip(trials)
+            .map(|((report, _), trial)| TrialResult {
+                trial: trial.id,
+                params: trial.params,
+                epochs,
+                score: self.monitor.report_value(&report),
+                report,
+            })
+            .collect();
+        SearchResults {
+            monitor: self.monitor,
+            results,
+        }
+    }
+
+    // trains every trial until it has been trained for epochs in total
+    // returns its evaluation of val and, if keep_checkpoints, its trainer state
+    fn run_trials<F>(
+        &self,
+        trials: &[Trial],
+        epochs: u32,
+        keep_checkpoints: bool,
+        build_trainer: &F,
+    ) -> Vec<(EvalReport, Option<Vec<u8>>)>
+    where
+        F: Fn(&Params) -> Trainer + Sync,
+    {
+        let next_trial = AtomicUsize::new(0);
+        let results = Mutex::new((0..trials.len()).map(|_| None).collect::<Vec<_>>());
+
+        thread::scope(|scope| {
+            for _ in 0..self.num_threads.min(trials.len()) {
+                scope.spawn(|| {
+                    loop {
+                        let idx = next_trial.fetch_add(1, atomic::Ordering::Relaxed);
+                        let Some(trial) = trials.get(idx) else {
+                            break;
+                        };
+
+                        let mut trainer = build_trainer(&trial.params);
+                        trainer.set_show_progress(false);
+                        if let Some(checkpoint) = &trial.checkpoint {
+                            trainer
+                                .load_checkpoint(checkpoint)
+                                .expect("Please build the same trainer for the same params");
+                        }
+                        if let Err(err) = trainer.fit(self.train, Some(self.val), epochs) {
+                            println!("Trial {} stopped: {}", trial.id, err);
+                        }
+
+                        let report = trainer.evaluate(self.val);
+                        let checkpoint = keep_checkpoints.then(|| {
+                            trainer
+                                .checkpoint()
+                                .expect("Could not serialize trial checkpoint")
+                        });
+                        println!(
+                            "Trial {} after {} epochs: {}",
+                            trial.id,
+                            epochs,
+                            self.monitor.report_value(&report)
+                        );
+                        results.lock().unwrap()[idx] = Some((report, checkpoint));
+                    }
+                });
+            }
+        });
+
+        results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect()
+    }
+}
@@ -43,6 +43,15 @@ impl DenseLayer {
         self.buffer_offset as usize..(self.buffer_offset + self.num_params()) as usize
     }
 
+    pub fn weight_range(&self) -> Range<usize> {
+        self.buffer_offset as usize..(self.buffer_offset + self.num_weights()) as usize
+    }
+
+    pub fn bias_range(&self) -> Range<usize> {
+        let bias_offset = self.buffer_offset + self.num_weights();
+        bias_offset as usize..(bias_offset + self.num_biases()) as usize
+    }
+
     pub fn init_rand(&self, param_buffer: &mut [f32]) {
         assert!(param_buffer.len() == self.num_params() as usize);
 
@@ -92,27 +101,20 @@ impl DenseLayer {
         }
     }
 
-    pub fn backward(
+    // param gradients are added onto result_grads so they can be accumulated over several batches
+    pub fn accumulate_param_grads(
         &self,
-        param_buffer: &[f32],
         output_grads: &[f32],
         inputs: &[f32],
         result_grads: &mut [f32],
-        input_grads: &mut [f32],
         batch_size: u32,
     ) {
-        assert!(param_buffer.len() == self.num_params() as usize);
         assert!(output_grads.len() == (batch_size * self.output_size) as usize);
         assert!(inputs.len() == (batch_size * self.input_size) as usize);
         assert!(result_grads.len() == self.num_params() as usize);
-        assert!(input_grads.len() == (batch_size * self.input_size) as usize);
-
-        let (weights, _biases) = param_buffer.split_at(self.num_weights() as usize);
 
         let (weight_grads, bias_grads) = result_grads.split_at_mut(self.num_weights() as usize);
 
-        bias_grads.fill(0.0);
-
         // bias gradients
         for i in 0..batch_size as usize {
             for j in 0..self.output_size as usize {
@@ -133,12 +135,26 @@ impl DenseLayer {
                 inputs.as_ptr(),
                 self.input_size as isize,
                 1,
-                0.0,
+                1.0,
                 weight_grads.as_mut_ptr(),
                 self.input_size as isize,
                 1,
             );
         }
+    }
+
+    pub fn backward_inputs(
+        &self,
+        param_buffer: &[f32],
+        output_grads: &[f32],
+        input_grads: &mut [f32],
+        batch_size: u32,
+    ) {
+        assert!(param_buffer.len() == self.num_params() as usize);
+        assert!(output_grads.len() == (batch_size * self.output_size) as usize);
+        assert!(input_grads.len() == (batch_size * self.input_size) as usize);
+
+        let (weights, _biases) = param_buffer.split_at(self.num_weights() as usize);
 
         // input gradients
         unsafe {
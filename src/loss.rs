@@ -1,10 +1,135 @@
+mod binary_cross_entropy;
+mod closure;
+mod composite;
+mod contrastive;
 mod cross_entropy;
+mod distance;
+mod focal;
+mod huber;
+mod kl_divergence;
+mod l1;
+mod log_cosh;
 mod mse;
+mod sigmoid_mse;
+mod smooth_l1;
+mod triplet;
 
+pub use binary_cross_entropy::BinaryCrossEntropy;
+pub use closure::ClosureLoss;
+pub use composite::CompositeLoss;
+pub use contrastive::ContrastiveLoss;
 pub use cross_entropy::CrossEntropy;
+pub use distance::Distance;
+pub use focal::FocalLoss;
+pub use huber::Huber;
+pub use kl_divergence::KlDivergence;
+pub use l1::L1;
+pub use log_cosh::LogCosh;
 pub use mse::Mse;
+pub use sigmoid_mse::SigmoidMse;
+pub use smooth_l1::SmoothL1;
+pub use triplet::TripletLoss;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Reduction {
+    Mean,
+    Sum,
+}
 
 pub trait Loss {
+    // inputs and targets of a single sample
     fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32;
     fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]);
+
+    // inputs, targets and grads hold batch_size rows, one per sample
+    // weights optionally scales the loss of each sample
+    fn forward_batch(
+        &self,
+        inputs: &[f32],
+        targets: &[f32],
+        weights: Option<&[f32]>,
+        reduction: Reduction,
+        batch_size: u32,
+    ) -> f32 {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len().is_multiple_of(batch_size as usize));
+        if let Some(weights) = weights {
+            assert!(weights.len() == batch_size as usize);
+        }
+
+        let output_size = inputs.len() / batch_size as usize;
+        let rows = inputs
+            .chunks(output_size)
+            .zip(targets.chunks(output_size))
+            .enumerate();
+
+        let mut loss = 0.0;
+        for (idx, (inputs, targets)) in rows {
+            let weight = weights.map_or(1.0, |weights| weights[idx]);
+            loss += weight * self.forward(inputs, targets);
+        }
+
+        match reduction {
+            Reduction::Mean => loss / batch_size as f32,
+            Reduction::Sum => loss,
+        }
+    }
+
+    fn backward_batch(
+        &self,
+        inputs: &[f32],
+        targets: &[f32],
+        grads: &mut [f32],
+        weights: Option<&[f32]>,
+        reduction: Reduction,
+        batch_size: u32,
+    ) {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+        assert!(inputs.len().is_multiple_of(batch_size as usize));
+        if let Some(weights) = weights {
+            assert!(weights.len() == batch_size as usize);
+        }
+
+        let scale = match reduction {
+            Reduction::Mean => 1.0 / batch_size as f32,
+            Reduction::Sum => 1.0,
+        };
+
+        let output_size = inputs.len() / batch_size as usize;
+        let rows = inputs
+            .chunks(output_size)
+            .zip(targets.chunks(output_size))
+            .zip(grads.chunks_mut(output_size))
+            .enumerate();
+
+        for (idx, ((inputs, targets), grads)) in rows {
+            self.backward(inputs, targets, grads);
+
+            let weight = weights.map_or(1.0, |weights| weights[idx]) * scale;
+            if weight != 1.0 {
+                for grad in grads {
+                    *grad *= weight;
+                }
+            }
+        }
+    }
+}
+
+// returns the max logit and log(sum(exp(logit - max)))
+fn log_sum_exp(logits: &[f32]) -> (f32, f32) {
+    let mut max = logits[0];
+    for logit in &logits[1..] {
+        max = max.max(*logit);
+    }
+
+    let mut exp_sum = 0.0;
+    for logit in logits {
+        exp_sum += (logit - max).exp();
+    }
+    (max, exp_sum.ln())
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
 }
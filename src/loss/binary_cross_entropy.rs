@@ -0,0 +1,55 @@
+use crate::loss::{Loss, sigmoid};
+
+// operates on logits, each output is an independent sigmoid
+pub struct BinaryCrossEntropy {
+    pos_weights: Option<Vec<f32>>,
+}
+
+impl BinaryCrossEntropy {
+    pub fn new() -> Self {
+        Self { pos_weights: None }
+    }
+
+    // pos_weights has one weight per output, scaling the loss of positive targets for that output
+    pub fn with_pos_weights(pos_weights: Vec<f32>) -> Self {
+        Self {
+            pos_weights: Some(pos_weights),
+        }
+    }
+
+    fn pos_weight(&self, idx: usize) -> f32 {
+        match &self.pos_weights {
+            Some(pos_weights) => pos_weights[idx],
+            None => 1.0,
+        }
+    }
+}
+
+// ln(1 + e^x) without overflow
+fn softplus(x: f32) -> f32 {
+    x.max(0.0) + (-x.abs()).exp().ln_1p()
+}
+
+impl Loss for BinaryCrossEntropy {
+    fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
+        assert!(inputs.len() == targets.len());
+
+        let mut loss = 0.0;
+        for (idx, (input, target)) in inputs.iter().zip(targets.iter()).enumerate() {
+            // -log(sigmoid(x)) = softplus(-x), -log(1 - sigmoid(x)) = softplus(x)
+            loss += self.pos_weight(idx) * target * softplus(-input)
+                + (1.0 - target) * softplus(*input);
+        }
+        loss
+    }
+
+    fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+
+        for (i, v) in grads.iter_mut().enumerate() {
+            let sig = sigmoid(inputs[i]);
+            *v = self.pos_weight(i) * targets[i] * (sig - 1.0) + (1.0 - targets[i]) * sig;
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::loss::Loss;
+
+type ForwardFn = dyn Fn(&[f32], &[f32]) -> f32;
+type BackwardFn = dyn Fn(&[f32], &[f32], &mut [f32]);
+
+// user provided loss, the closures have the same signatures as Loss::forward and Loss::backward
+pub struct ClosureLoss {
+    forward: Box<ForwardFn>,
+    backward: Box<BackwardFn>,
+}
+
+impl ClosureLoss {
+    pub fn new<F, B>(forward: F, backward: B) -> Self
+    where
+        F: Fn(&[f32], &[f32]) -> f32 + 'static,
+        B: Fn(&[f32], &[f32], &mut [f32]) + 'static,
+    {
+        Self {
+            forward: Box::new(forward),
+            backward: Box::new(backward),
+        }
+    }
+}
+
+impl Loss for ClosureLoss {
+    fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
+        (self.forward)(inputs, targets)
+    }
+
+    fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
+        (self.backward)(inputs, targets, grads);
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::Range;
+
+use crate::loss::Loss;
+
+struct Term {
+    loss_fn: Box<dyn Loss>,
+    weight: f32,
+    range: Range<usize>,
+}
+
+// weighted sum of losses, each applied to a slice of the outputs and targets
+pub struct CompositeLoss {
+    terms: Vec<Term>,
+}
+
+impl CompositeLoss {
+    pub fn new() -> Self {
+        Self { terms: Vec::new() }
+    }
+
+    pub fn add<L: Loss + 'static>(mut self, loss_fn: L, weight: f32, range: Range<usize>) -> Self {
+        assert!(
+            range.start < range.end,
+            "Composite loss range {:?} is empty",
+            range
+        );
+        self.terms.push(Term {
+            loss_fn: Box::new(loss_fn),
+            weight,
+            range,
+        });
+        self
+    }
+
+    // number of outputs the ranges of the terms cover
+    pub fn output_size(&self) -> usize {
+        self.terms
+            .iter()
+            .map(|term| term.range.end)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Loss for CompositeLoss {
+    fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
+        assert!(inputs.len() == targets.len());
+
+        let mut loss = 0.0;
+        for term in &self.terms {
+            loss += term.weight
+                * term
+                    .loss_fn
+                    .forward(&inputs[term.range.clone()], &targets[term.range.clone()]);
+        }
+        loss
+    }
+
+    fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+
+        grads.fill(0.0);
+
+        // ranges may overlap, so each term's gradients are added on
+        let mut term_grads = Vec::new();
+        for term in &self.terms {
+            term_grads.resize(term.range.len(), 0.0);
+            term.loss_fn.backward(
+                &inputs[term.range.clone()],
+                &targets[term.range.clone()],
+                &mut term_grads,
+            );
+            for (grad, term_grad) in grads[term.range.clone()].iter_mut().zip(term_grads.iter()) {
+                *grad += term.weight * term_grad;
+            }
+        }
+    }
+}
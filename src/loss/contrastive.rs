@@ -0,0 +1,45 @@
+use crate::loss::Distance;
+
+// pulls the embeddings of similar pairs together and pushes dissimilar pairs at least margin apart
+pub struct ContrastiveLoss {
+    margin: f32,
+    distance: Distance,
+}
+
+impl ContrastiveLoss {
+    pub fn new(margin: f32, distance: Distance) -> Self {
+        Self { margin, distance }
+    }
+
+    pub fn forward(&self, first: &[f32], second: &[f32], similar: bool) -> f32 {
+        let dist = self.distance.forward(first, second);
+        if similar {
+            0.5 * dist * dist
+        } else {
+            let gap = (self.margin - dist).max(0.0);
+            0.5 * gap * gap
+        }
+    }
+
+    // grads are added onto first_grads and second_grads
+    pub fn backward(
+        &self,
+        first: &[f32],
+        second: &[f32],
+        similar: bool,
+        first_grads: &mut [f32],
+        second_grads: &mut [f32],
+    ) {
+        let dist = self.distance.forward(first, second);
+        let dist_grad = if similar {
+            dist
+        } else {
+            -(self.margin - dist).max(0.0)
+        };
+
+        if dist_grad != 0.0 {
+            self.distance
+                .backward(first, second, dist_grad, first_grads, second_grads);
+        }
+    }
+}
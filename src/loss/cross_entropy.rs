@@ -1,37 +1,69 @@
-use crate::loss::Loss;
+use crate::loss::{Loss, log_sum_exp};
 
-pub struct CrossEntropy {}
+// softmax cross entropy on logits
+pub struct CrossEntropy {
+    label_smoothing: f32,
+    class_weights: Option<Vec<f32>>,
+}
 
 impl CrossEntropy {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            label_smoothing: 0.0,
+            class_weights: None,
+        }
+    }
+
+    // mixes the targets with a uniform distribution over the classes
+    pub fn label_smoothing(mut self, epsilon: f32) -> Self {
+        assert!((0.0..1.0).contains(&epsilon));
+        self.label_smoothing = epsilon;
+        self
+    }
+
+    pub fn class_weights(mut self, class_weights: Vec<f32>) -> Self {
+        self.class_weights = Some(class_weights);
+        self
+    }
+
+    fn weighted_target(&self, targets: &[f32], class: usize) -> f32 {
+        let smoothed = (1.0 - self.label_smoothing) * targets[class]
+            + self.label_smoothing / targets.len() as f32;
+        match &self.class_weights {
+            Some(class_weights) => smoothed * class_weights[class],
+            None => smoothed,
+        }
     }
 }
 
 impl Loss for CrossEntropy {
     fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
-        let mut max = inputs[0];
-        for i in 1..inputs.len() {
-            max = max.max(inputs[i]);
-        }
-
-        let mut exp_sum = 0.0;
-        for input in inputs {
-            exp_sum += (input - max).exp();
-        }
+        assert!(inputs.len() == targets.len());
 
-        let log_exp_sum = exp_sum.ln();
+        let (max, log_exp_sum) = log_sum_exp(inputs);
 
         let mut loss = 0.0;
-        for (input, target) in inputs.iter().zip(targets.iter()) {
-            loss -= target * (input - max - log_exp_sum);
+        for (class, input) in inputs.iter().enumerate() {
+            loss -= self.weighted_target(targets, class) * (input - max - log_exp_sum);
         }
         loss
     }
 
     fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
-        for (i, v) in grads.iter_mut().enumerate() {
-            *v = inputs[i] - targets[i];
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+
+        let (max, log_exp_sum) = log_sum_exp(inputs);
+
+        // with weighted targets t, d/dx_j of -sum(t_c * log(p_c)) is p_j * sum(t) - t_j
+        let mut target_sum = 0.0;
+        for class in 0..targets.len() {
+            target_sum += self.weighted_target(targets, class);
+        }
+
+        for (class, grad) in grads.iter_mut().enumerate() {
+            let prob = (inputs[class] - max - log_exp_sum).exp();
+            *grad = prob * target_sum - self.weighted_target(targets, class);
         }
     }
 }
@@ -0,0 +1,89 @@
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Distance {
+    Euclidean,
+    SquaredEuclidean,
+    // 1 - cosine similarity
+    Cosine,
+}
+
+impl Distance {
+    pub fn forward(&self, a: &[f32], b: &[f32]) -> f32 {
+        assert!(a.len() == b.len());
+
+        match self {
+            Self::Euclidean => squared_euclidean(a, b).sqrt(),
+            Self::SquaredEuclidean => squared_euclidean(a, b),
+            Self::Cosine => {
+                let (dot, norm_a, norm_b) = dot_and_norms(a, b);
+                1.0 - dot / (norm_a * norm_b).max(f32::EPSILON)
+            }
+        }
+    }
+
+    // adds scale * d(distance)/da to grads_a and scale * d(distance)/db to grads_b
+    pub fn backward(
+        &self,
+        a: &[f32],
+        b: &[f32],
+        scale: f32,
+        grads_a: &mut [f32],
+        grads_b: &mut [f32],
+    ) {
+        assert!(a.len() == b.len());
+        assert!(grads_a.len() == a.len());
+        assert!(grads_b.len() == b.len());
+
+        match self {
+            Self::Euclidean => {
+                let dist = squared_euclidean(a, b).sqrt();
+                // the gradient is undefined at 0, treat it as 0
+                if dist == 0.0 {
+                    return;
+                }
+                for i in 0..a.len() {
+                    let grad = scale * (a[i] - b[i]) / dist;
+                    grads_a[i] += grad;
+                    grads_b[i] -= grad;
+                }
+            }
+            Self::SquaredEuclidean => {
+                for i in 0..a.len() {
+                    let grad = scale * 2.0 * (a[i] - b[i]);
+                    grads_a[i] += grad;
+                    grads_b[i] -= grad;
+                }
+            }
+            Self::Cosine => {
+                let (dot, norm_a, norm_b) = dot_and_norms(a, b);
+                let norm_product = (norm_a * norm_b).max(f32::EPSILON);
+                let norm_a_sq = (norm_a * norm_a).max(f32::EPSILON);
+                let norm_b_sq = (norm_b * norm_b).max(f32::EPSILON);
+                let cosine = dot / norm_product;
+                for i in 0..a.len() {
+                    grads_a[i] -= scale * (b[i] / norm_product - cosine * a[i] / norm_a_sq);
+                    grads_b[i] -= scale * (a[i] / norm_product - cosine * b[i] / norm_b_sq);
+                }
+            }
+        }
+    }
+}
+
+fn squared_euclidean(a: &[f32], b: &[f32]) -> f32 {
+    let mut result = 0.0;
+    for (x, y) in a.iter().zip(b.iter()) {
+        result += (x - y) * (x - y);
+    }
+    result
+}
+
+fn dot_and_norms(a: &[f32], b: &[f32]) -> (f32, f32, f32) {
+    let mut dot = 0.0;
+    let mut norm_a = 0.0;
+    let mut norm_b = 0.0;
+    for (x, y) in a.iter().zip(b.iter()) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    (dot, norm_a.sqrt(), norm_b.sqrt())
+}
@@ -0,0 +1,72 @@
+use crate::loss::{Loss, log_sum_exp};
+
+// softmax focal loss on logits
+// down-weights well classified samples by (1 - p)^gamma, gamma = 0 is cross entropy
+pub struct FocalLoss {
+    gamma: f32,
+    alpha: Option<Vec<f32>>,
+}
+
+impl FocalLoss {
+    pub fn new(gamma: f32) -> Self {
+        assert!(gamma >= 0.0);
+        Self { gamma, alpha: None }
+    }
+
+    // per class weights
+    pub fn alpha(mut self, alpha: Vec<f32>) -> Self {
+        self.alpha = Some(alpha);
+        self
+    }
+
+    fn class_alpha(&self, class: usize) -> f32 {
+        match &self.alpha {
+            Some(alpha) => alpha[class],
+            None => 1.0,
+        }
+    }
+}
+
+impl Loss for FocalLoss {
+    fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
+        assert!(inputs.len() == targets.len());
+
+        let (max, log_exp_sum) = log_sum_exp(inputs);
+
+        let mut loss = 0.0;
+        for (class, (input, target)) in inputs.iter().zip(targets.iter()).enumerate() {
+            let log_prob = input - max - log_exp_sum;
+            let focus = (1.0 - log_prob.exp()).powf(self.gamma);
+            loss -= self.class_alpha(class) * target * focus * log_prob;
+        }
+        loss
+    }
+
+    fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+
+        let (max, log_exp_sum) = log_sum_exp(inputs);
+
+        // g_c = dL/dp_c * p_c, then by the softmax jacobian dL/dx_j = g_j - p_j * sum(g)
+        let mut g_sum = 0.0;
+        for (class, grad) in grads.iter_mut().enumerate() {
+            let log_prob = inputs[class] - max - log_exp_sum;
+            let prob = log_prob.exp();
+            // clamped so (1 - p)^(gamma - 1) stays finite for gamma < 1
+            let one_minus_prob = (1.0 - prob).max(1e-12);
+
+            let g = -self.class_alpha(class)
+                * targets[class]
+                * (one_minus_prob.powf(self.gamma)
+                    - self.gamma * one_minus_prob.powf(self.gamma - 1.0) * prob * log_prob);
+            *grad = g;
+            g_sum += g;
+        }
+
+        for (class, grad) in grads.iter_mut().enumerate() {
+            let prob = (inputs[class] - max - log_exp_sum).exp();
+            *grad -= prob * g_sum;
+        }
+    }
+}
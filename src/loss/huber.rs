@@ -0,0 +1,40 @@
+use crate::loss::Loss;
+
+// quadratic for errors up to delta, linear beyond it
+pub struct Huber {
+    delta: f32,
+}
+
+impl Huber {
+    pub fn new(delta: f32) -> Self {
+        assert!(delta > 0.0);
+        Self { delta }
+    }
+}
+
+impl Loss for Huber {
+    fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
+        assert!(inputs.len() == targets.len());
+
+        let mut result = 0.0;
+        for (input, target) in inputs.iter().zip(targets.iter()) {
+            let diff = (*input - *target).abs();
+            if diff <= self.delta {
+                result += 0.5 * diff * diff;
+            } else {
+                result += self.delta * (diff - 0.5 * self.delta);
+            }
+        }
+        result
+    }
+
+    fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+
+        for (i, v) in grads.iter_mut().enumerate() {
+            let diff = inputs[i] - targets[i];
+            *v = diff.clamp(-self.delta, self.delta);
+        }
+    }
+}
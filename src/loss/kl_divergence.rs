@@ -0,0 +1,68 @@
+use crate::loss::{Loss, log_sum_exp};
+
+// KL(targets || softmax(inputs / temperature)) on logits, targets are probabilities
+// scaled by temperature^2 so gradient magnitudes don't shrink as the temperature grows
+pub struct KlDivergence {
+    temperature: f32,
+}
+
+impl KlDivergence {
+    pub fn new(temperature: f32) -> Self {
+        assert!(temperature > 0.0);
+        Self { temperature }
+    }
+
+    pub fn temperature(&self) -> f32 {
+        self.temperature
+    }
+
+    // softmax(teacher_logits / temperature) of a single sample
+    pub fn soft_targets(&self, teacher_logits: &[f32], targets: &mut [f32]) {
+        assert!(teacher_logits.len() == targets.len());
+
+        let logits = self.scaled_logits(teacher_logits);
+        let (max, log_exp_sum) = log_sum_exp(&logits);
+        for (target, logit) in targets.iter_mut().zip(logits.iter()) {
+            *target = (logit - max - log_exp_sum).exp();
+        }
+    }
+
+    fn scaled_logits(&self, inputs: &[f32]) -> Vec<f32> {
+        inputs
+            .iter()
+            .map(|input| input / self.temperature)
+            .collect()
+    }
+}
+
+impl Loss for KlDivergence {
+    fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
+        assert!(inputs.len() == targets.len());
+
+        let logits = self.scaled_logits(inputs);
+        let (max, log_exp_sum) = log_sum_exp(&logits);
+
+        let mut loss = 0.0;
+        for (logit, target) in logits.iter().zip(targets.iter()) {
+            // 0 * log(0) is 0
+            if *target > 0.0 {
+                loss += target * (target.ln() - (logit - max - log_exp_sum));
+            }
+        }
+        loss * self.temperature * self.temperature
+    }
+
+    fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+
+        let logits = self.scaled_logits(inputs);
+        let (max, log_exp_sum) = log_sum_exp(&logits);
+
+        let target_sum: f32 = targets.iter().sum();
+        for (i, v) in grads.iter_mut().enumerate() {
+            let prob = (logits[i] - max - log_exp_sum).exp();
+            *v = self.temperature * (prob * target_sum - targets[i]);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::loss::Loss;
+
+pub struct L1 {}
+
+impl L1 {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Loss for L1 {
+    fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
+        assert!(inputs.len() == targets.len());
+
+        let mut result = 0.0;
+        for (input, target) in inputs.iter().zip(targets.iter()) {
+            result += (*input - *target).abs();
+        }
+        result
+    }
+
+    fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+
+        for (i, v) in grads.iter_mut().enumerate() {
+            let diff = inputs[i] - targets[i];
+            *v = if diff > 0.0 {
+                1.0
+            } else if diff < 0.0 {
+                -1.0
+            } else {
+                0.0
+            };
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::f32::consts;
+
+use crate::loss::Loss;
+
+pub struct LogCosh {}
+
+impl LogCosh {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Loss for LogCosh {
+    fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
+        assert!(inputs.len() == targets.len());
+
+        let mut result = 0.0;
+        for (input, target) in inputs.iter().zip(targets.iter()) {
+            let diff = (*input - *target).abs();
+            // log(cosh(x)) = |x| + log(1 + e^(-2|x|)) - log(2), which doesn't overflow
+            result += diff + (-2.0 * diff).exp().ln_1p() - consts::LN_2;
+        }
+        result
+    }
+
+    fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+
+        for (i, v) in grads.iter_mut().enumerate() {
+            *v = (inputs[i] - targets[i]).tanh();
+        }
+    }
+}
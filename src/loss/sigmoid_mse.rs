@@ -0,0 +1,42 @@
+use crate::loss::{Loss, sigmoid};
+
+// mse between sigmoid(input * scale) and targets in [0, 1]
+pub struct SigmoidMse {
+    scale: f32,
+}
+
+impl SigmoidMse {
+    pub fn new(scale: f32) -> Self {
+        Self { scale }
+    }
+
+    // blends a game result (0 loss, 0.5 draw, 1 win) with a score mapped through the same sigmoid
+    // result_weight = 1 only uses the result, result_weight = 0 only uses the score
+    pub fn blend_target(&self, result: f32, score: f32, result_weight: f32) -> f32 {
+        assert!((0.0..=1.0).contains(&result_weight));
+        result_weight * result + (1.0 - result_weight) * sigmoid(score * self.scale)
+    }
+}
+
+impl Loss for SigmoidMse {
+    fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
+        assert!(inputs.len() == targets.len());
+
+        let mut result = 0.0;
+        for (input, target) in inputs.iter().zip(targets.iter()) {
+            let diff = sigmoid(*input * self.scale) - *target;
+            result += diff * diff;
+        }
+        result
+    }
+
+    fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+
+        for (i, v) in grads.iter_mut().enumerate() {
+            let sig = sigmoid(inputs[i] * self.scale);
+            *v = 2.0 * (sig - targets[i]) * sig * (1.0 - sig) * self.scale;
+        }
+    }
+}
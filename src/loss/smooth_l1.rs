@@ -0,0 +1,40 @@
+use crate::loss::Loss;
+
+// huber loss divided by beta, so the linear region always has slope 1
+pub struct SmoothL1 {
+    beta: f32,
+}
+
+impl SmoothL1 {
+    pub fn new(beta: f32) -> Self {
+        assert!(beta > 0.0);
+        Self { beta }
+    }
+}
+
+impl Loss for SmoothL1 {
+    fn forward(&self, inputs: &[f32], targets: &[f32]) -> f32 {
+        assert!(inputs.len() == targets.len());
+
+        let mut result = 0.0;
+        for (input, target) in inputs.iter().zip(targets.iter()) {
+            let diff = (*input - *target).abs();
+            if diff < self.beta {
+                result += 0.5 * diff * diff / self.beta;
+            } else {
+                result += diff - 0.5 * self.beta;
+            }
+        }
+        result
+    }
+
+    fn backward(&self, inputs: &[f32], targets: &[f32], grads: &mut [f32]) {
+        assert!(inputs.len() == targets.len());
+        assert!(inputs.len() == grads.len());
+
+        for (i, v) in grads.iter_mut().enumerate() {
+            let diff = inputs[i] - targets[i];
+            *v = (diff / self.beta).clamp(-1.0, 1.0);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::loss::Distance;
+
+// wants the anchor to be closer to the positive than to the negative by at least margin
+pub struct TripletLoss {
+    margin: f32,
+    distance: Distance,
+}
+
+impl TripletLoss {
+    pub fn new(margin: f32, distance: Distance) -> Self {
+        Self { margin, distance }
+    }
+
+    pub fn forward(&self, anchor: &[f32], positive: &[f32], negative: &[f32]) -> f32 {
+        let positive_dist = self.distance.forward(anchor, positive);
+        let negative_dist = self.distance.forward(anchor, negative);
+        (positive_dist - negative_dist + self.margin).max(0.0)
+    }
+
+    // grads are added onto anchor_grads, positive_grads and negative_grads
+    pub fn backward(
+        &self,
+        anchor: &[f32],
+        positive: &[f32],
+        negative: &[f32],
+        anchor_grads: &mut [f32],
+        positive_grads: &mut [f32],
+        negative_grads: &mut [f32],
+    ) {
+        if self.forward(anchor, positive, negative) <= 0.0 {
+            return;
+        }
+
+        self.distance
+            .backward(anchor, positive, 1.0, anchor_grads, positive_grads);
+        self.distance
+            .backward(anchor, negative, -1.0, anchor_grads, negative_grads);
+    }
+}
@@ -2,11 +2,9 @@ use std::{
     f32::consts,
     fs::{self, File},
     io::{ErrorKind, Read, Write},
-    time::Instant,
 };
 
-use indicatif::ProgressBar;
-use rand::seq::SliceRandom;
+use rand::{SeedableRng, rngs::StdRng};
 use raylib::{
     color::Color,
     ffi::{KeyboardKey, MouseButton},
@@ -14,14 +12,25 @@ use raylib::{
 };
 
 use crate::{
+    callback::{LogFormat, MetricsLogger, ModelCheckpoint, Monitor, TensorBoardLogger},
+    hyper_search::{HyperSearch, SearchSpace},
     network::{Network, NetworkBuilder},
     trainer::{Trainer, TrainerBuilder},
 };
 
+mod average;
+mod callback;
+mod cross_validation;
+mod eval;
+mod grad_check;
+mod health;
+mod hyper_search;
 mod layer;
 mod loss;
 mod network;
 mod optim;
+mod sampler;
+mod tensorboard;
 mod trainer;
 
 #[derive(Clone)]
@@ -30,8 +39,22 @@ struct DataPoint {
     target: Vec<f32>,
 }
 
+#[derive(Clone)]
+struct Triplet {
+    anchor: Vec<f32>,
+    positive: Vec<f32>,
+    negative: Vec<f32>,
+}
+
+#[derive(Clone)]
+struct Pair {
+    first: Vec<f32>,
+    second: Vec<f32>,
+    similar: bool,
+}
+
 fn max_index(t: &[f32]) -> usize {
-    let mut max_val = -1.0;
+    let mut max_val = f32::NEG_INFINITY;
     let mut max_idx = 0;
     for (idx, val) in t.iter().enumerate() {
         if *val > max_val {
@@ -42,41 +65,13 @@ fn max_index(t: &[f32]) -> usize {
     max_idx
 }
 
-fn print_network_stats(
-    trainer: &mut Trainer,
-    dataset: &Vec<DataPoint>,
-    test_dataset: &Vec<DataPoint>,
-) {
-    let mut total_loss = 0.0;
-    let mut total_correct = 0;
-    for data_pt in dataset {
-        let output = trainer.network().forward_inference(&data_pt.input);
-        let loss = trainer.loss_fn().forward(&output, &data_pt.target);
-        total_loss += loss;
-        if max_index(&output) == max_index(&data_pt.target) {
-            total_correct += 1;
-        }
-    }
-
-    let mut total_test_loss = 0.0;
-    let mut total_test_correct = 0;
-    for data_pt in test_dataset {
-        let output = trainer.network().forward_inference(&data_pt.input);
-        let loss = trainer.loss_fn().forward(&output, &data_pt.target);
-        total_test_loss += loss;
-        if max_index(&output) == max_index(&data_pt.target) {
-            total_test_correct += 1;
-        }
-    }
-
-    let loss = total_loss / dataset.len() as f32;
-    let accuracy = total_correct as f32 / dataset.len() as f32;
-    let test_loss = total_test_loss / test_dataset.len() as f32;
-    let test_accuracy = total_test_correct as f32 / test_dataset.len() as f32;
-    println!("Network loss: {}", loss);
-    println!("Network accuracy: {}", accuracy);
-    println!("Network test loss: {}", test_loss);
-    println!("Network test accuracy: {}", test_accuracy);
+fn print_network_stats(trainer: &Trainer, dataset: &[DataPoint], val_dataset: &[DataPoint]) {
+    let report = trainer.evaluate(dataset);
+    let val_report = trainer.evaluate(val_dataset);
+    println!("Network loss: {}", report.loss);
+    println!("Network accuracy: {}", report.accuracy);
+    println!("Network validation loss: {}", val_report.loss);
+    println!("Network validation accuracy: {}", val_report.accuracy);
 }
 
 fn load_mnist_dataset(image_file: &str, label_file: &str) -> Option<Vec<DataPoint>> {
@@ -385,6 +380,17 @@ fn visualize_augmentations(dataset: &Vec<DataPoint>) {
     }
 }
 
+const SPLIT_SEED: u64 = 0;
+
+fn mnist_network() -> NetworkBuilder {
+    NetworkBuilder::new(784)
+        .add_dense_layer(256)
+        .add_relu()
+        .add_dense_layer(64)
+        .add_relu()
+        .add_dense_layer(10)
+}
+
 fn main() {
     match File::open("mnist-net.bin") {
         Ok(file) => {
@@ -406,7 +412,7 @@ fn main() {
 
     let args: Vec<String> = std::env::args().collect();
     let mnist_dir = args[1].clone();
-    let mut dataset = load_mnist_dataset(
+    let dataset = load_mnist_dataset(
         (mnist_dir.clone() + "/train-images-idx3-ubyte").as_str(),
         (mnist_dir.clone() + "/train-labels-idx1-ubyte").as_str(),
     )
@@ -423,57 +429,151 @@ fn main() {
         return;
     }
 
+    const BATCH_SIZE: u32 = 32;
+
+    if args.len() > 2 && args[2] == "cross-validate" {
+        println!("Cross-validating the network");
+        let report = cross_validation::cross_validate(
+            &dataset,
+            5,
+            10,
+            SPLIT_SEED,
+            mnist_network,
+            |network| {
+                TrainerBuilder::new(network)
+                    .adamw(0.001, 0.003)
+                    .batch_size(BATCH_SIZE)
+                    .cross_entropy()
+                    .augmenter(augment_image)
+                    .build()
+            },
+        );
+        report.print();
+        return;
+    }
+
+    // the test set is only used for the final evaluation, model selection uses the validation set
+    let (train_dataset, val_dataset) =
+        cross_validation::stratified_split(&dataset, 0.1, &mut StdRng::seed_from_u64(SPLIT_SEED));
+
+    if args.len() > 2 && args[2] == "search" {
+        println!("Searching hyperparameters");
+        let space = SearchSpace::new()
+            .log_uniform("lr", 0.0001, 0.01)
+            .log_uniform("weight_decay", 0.0001, 0.1)
+            .choice("hidden1", vec![128.0, 256.0, 512.0])
+            .choice("hidden2", vec![32.0, 64.0, 128.0]);
+        let results = HyperSearch::new(&train_dataset, &val_dataset)
+            .seed(SPLIT_SEED)
+            .successive_halving(&space, 27, 1, 3, |params| {
+                let mut network = NetworkBuilder::new(784)
+                    .add_dense_layer(params.get("hidden1") as u32)
+                    .add_relu()
+                    .add_dense_layer(params.get("hidden2") as u32)
+                    .add_relu()
+                    .add_dense_layer(10)
+                    .build();
+                network.init_rand();
+                TrainerBuilder::new(network)
+                    .adamw(params.get("lr"), params.get("weight_decay"))
+                    .batch_size(BATCH_SIZE)
+                    .cross_entropy()
+                    .augmenter(augment_image)
+                    .build()
+            });
+        results.print();
+        if let Err(err) = results.save_csv("mnist-search.csv") {
+            println!("Error writing search results: {}", err);
+        }
+        return;
+    }
+
     println!("Training a network");
 
-    let mut network = NetworkBuilder::new(784)
-        .add_dense_layer(256)
-        .add_relu()
-        .add_dense_layer(64)
-        .add_relu()
-        .add_dense_layer(10)
-        .build();
+    let mut network = mnist_network().build();
     network.init_rand();
 
-    const BATCH_SIZE: u32 = 32;
+    const CHECKPOINT_DIR: &str = "mnist-checkpoints";
+    const LOG_DIR: &str = "mnist-logs";
+    const TENSORBOARD_DIR: &str = "mnist-runs";
 
     // let mut trainer = Trainer::new(network, BATCH_SIZE);
     let mut trainer = TrainerBuilder::new(network)
         .adamw(0.001, 0.003)
         .batch_size(BATCH_SIZE)
         .cross_entropy()
+        .augmenter(augment_image)
+        .check_finite(true)
+        .checkpoint_dir(CHECKPOINT_DIR, Monitor::ValAccuracy)
+        .early_stopping(Monitor::ValAccuracy, 10, 0.0005)
+        .callback(
+            MetricsLogger::new(LOG_DIR, LogFormat::Csv)
+                .expect("Could not create metrics log files"),
+        )
+        .callback(
+            TensorBoardLogger::new(TENSORBOARD_DIR)
+                .expect("Could not create tensorboard event file")
+                .log_every(50)
+                .sample_images(
+                    val_dataset[..8]
+                        .iter()
+                        .map(|data_pt| data_pt.input.clone())
+                        .collect(),
+                    28,
+                    28,
+                ),
+        )
+        .on_epoch_end(|trainer, metrics| {
+            println!("Epoch: {}", metrics.epoch);
+            println!("time: {}", metrics.seconds);
+            println!("samples/s: {}", metrics.samples_per_second);
+            println!("Network loss: {}", metrics.loss);
+            if let Some(val) = &metrics.val {
+                println!("Network validation loss: {}", val.loss);
+                println!("Network validation accuracy: {}", val.accuracy);
+            }
+            if let Some(health) = trainer.health() {
+                health.print();
+            }
+        })
         .build();
 
-    print_network_stats(&mut trainer, &dataset, &test_dataset);
-
-    dataset.shuffle(&mut rand::rng());
-
-    for i in 0..70 {
-        let num_batches = dataset.len() as u32 / BATCH_SIZE;
-        let start_time = Instant::now();
-
-        let bar = ProgressBar::new(num_batches as u64);
-        for i in 0..dataset.len() as u32 / BATCH_SIZE {
-            let begin = (i * BATCH_SIZE) as usize;
-            let end = ((i + 1) * BATCH_SIZE) as usize;
-            let batch = &dataset[begin..end];
-            trainer.run_batch_augmented(batch, augment_image);
-            bar.inc(1);
+    let checkpoint_file = ModelCheckpoint::latest_checkpoint_path(CHECKPOINT_DIR);
+    match trainer.resume_from(&checkpoint_file) {
+        Ok(()) => {
+            println!(
+                "Resuming from checkpoint {} at epoch {}",
+                checkpoint_file.display(),
+                trainer.epoch()
+            );
         }
+        Err(err) => {
+            if err.kind() != ErrorKind::NotFound {
+                println!(
+                    "Could not load checkpoint {}: {}",
+                    checkpoint_file.display(),
+                    err
+                );
+                return;
+            }
+        }
+    }
 
-        let end_time = Instant::now();
+    print_network_stats(&trainer, &train_dataset, &val_dataset);
 
-        bar.finish();
+    if let Err(err) = trainer.fit(&train_dataset, Some(&val_dataset), 70) {
+        println!("Training stopped: {}", err);
+    }
 
-        let seconds = (end_time - start_time).as_secs_f64();
-        println!("Epoch: {}", i);
-        println!("time: {}", seconds);
-        println!(
-            "batches/s: {}, samples/s: {}",
-            num_batches as f64 / seconds,
-            (num_batches * BATCH_SIZE) as f64 / seconds
-        );
-        print_network_stats(&mut trainer, &dataset, &test_dataset);
+    // keep the network from the epoch with the best validation accuracy
+    let best_file = ModelCheckpoint::best_network_path(CHECKPOINT_DIR);
+    if let Ok(file) = File::open(&best_file) {
+        println!("Using best network {}", best_file.display());
+        if let Some(network) = load_network(file) {
+            *trainer.network_mut() = network;
+        }
     }
+    trainer.evaluate(&test_dataset).print();
 
     let data = wincode::serialize(trainer.network()).expect("Could not serialize network");
     let mut net_file =
@@ -18,25 +18,30 @@ impl Network {
     }
 
     pub fn forward_inference(&self, inputs: &[f32]) -> Vec<f32> {
+        self.forward_batch(inputs, 1)
+    }
+
+    // inputs holds batch_size rows of inputs, returns batch_size rows of outputs
+    pub fn forward_batch(&self, inputs: &[f32], batch_size: u32) -> Vec<f32> {
         let mut input_buffer = inputs.to_vec();
         let mut output_buffer = Vec::new();
         for layer in &self.layers {
             match layer {
                 Layer::Dense(dense_layer) => {
-                    output_buffer.resize(dense_layer.output_size() as usize, 0.0);
+                    output_buffer.resize((dense_layer.output_size() * batch_size) as usize, 0.0);
                     dense_layer.forward(
                         &self.param_buffer[dense_layer.param_buffer_range()],
                         &input_buffer,
                         &mut output_buffer,
-                        1,
+                        batch_size,
                     );
 
                     input_buffer.resize(output_buffer.len(), 0.0);
                     input_buffer.copy_from_slice(&output_buffer);
                 }
                 Layer::ReLu(relu_layer) => {
-                    output_buffer.resize(relu_layer.size() as usize, 0.0);
-                    relu_layer.forward(&input_buffer, &mut output_buffer, 1);
+                    output_buffer.resize((relu_layer.size() * batch_size) as usize, 0.0);
+                    relu_layer.forward(&input_buffer, &mut output_buffer, batch_size);
 
                     input_buffer.resize(output_buffer.len(), 0.0);
                     input_buffer.copy_from_slice(&output_buffer);
@@ -1,11 +1,52 @@
 mod adam;
 mod adamw;
+mod lookahead;
+mod param_groups;
 mod sgd;
 
 pub use adam::*;
 pub use adamw::*;
+pub use lookahead::*;
+pub use param_groups::*;
 pub use sgd::*;
 
+use std::io::{self, ErrorKind};
+
+use wincode_derive::{SchemaRead, SchemaWrite};
+
+#[derive(Clone, SchemaRead, SchemaWrite)]
+pub struct OptimizerState {
+    // Optimizer::kind of the optimizer that wrote the state
+    pub kind: String,
+    pub step: u64,
+    pub buffers: Vec<Vec<f32>>,
+}
+
 pub trait Optimizer {
     fn update(&mut self, params: &mut [f32], grads: &[f32], batch_size: u32);
+    // base learning rate, before any param group scaling
+    fn lr(&self) -> f32;
+    fn param_groups_mut(&mut self) -> &mut ParamGroups;
+    fn kind(&self) -> String;
+    fn state(&self) -> OptimizerState;
+    // fails if the buffers were written for a different network
+    fn check_buffers(&self, buffers: &[Vec<f32>]) -> io::Result<()>;
+    // applies nothing unless check_state passes
+    fn load_state(&mut self, state: OptimizerState) -> io::Result<()>;
+
+    // fails if the state was written by a different optimizer or for a different network
+    fn check_state(&self, state: &OptimizerState) -> io::Result<()> {
+        if state.kind != self.kind() {
+            return Err(invalid_state(&format!(
+                "Optimizer state was written by {}, not {}",
+                state.kind,
+                self.kind()
+            )));
+        }
+        self.check_buffers(&state.buffers)
+    }
+}
+
+fn invalid_state(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
 }
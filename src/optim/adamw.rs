@@ -1,17 +1,30 @@
-use crate::{network::Network, optim::Optimizer};
+use std::io;
+
+use crate::{
+    network::Network,
+    optim::{Optimizer, OptimizerState, ParamGroups, invalid_state},
+};
 
 pub struct AdamW {
     lr: f32,
-    lambda: f32,
+    param_groups: ParamGroups,
+    step: u64,
     momentum: Vec<f32>,
     velocities: Vec<f32>,
 }
 
 impl AdamW {
     pub fn new(lr: f32, lambda: f32, network: &Network) -> Self {
+        // plain adamw decays the biases as well, like it did before param groups
+        let param_groups = ParamGroups::new(network, lambda).bias_decay(lambda);
+        Self::with_param_groups(lr, param_groups, network)
+    }
+
+    pub fn with_param_groups(lr: f32, param_groups: ParamGroups, network: &Network) -> Self {
         Self {
             lr,
-            lambda,
+            param_groups,
+            step: 0,
             momentum: vec![0.0; network.num_params() as usize],
             velocities: vec![0.0; network.num_params() as usize],
         }
@@ -24,17 +37,67 @@ impl Optimizer for AdamW {
         const BETA2: f32 = 0.999;
         const EPSILON: f32 = 0.00000001;
 
-        let lr = self.lr / batch_size as f32;
-        for (idx, param) in params.iter_mut().enumerate() {
-            let velocity = &mut self.velocities[idx];
-            let momentum = &mut self.momentum[idx];
-            let gradient = &grads[idx];
+        self.step += 1;
 
-            *momentum = BETA1 * *momentum + (1.0 - BETA1) * gradient;
-            *velocity = BETA2 * *velocity + (1.0 - BETA2) * gradient * gradient;
+        for group in self.param_groups.trainable_groups() {
+            let lr = self.lr * group.lr_scale() / batch_size as f32;
+            let lambda = group.weight_decay();
+            for idx in group.range() {
+                let param = &mut params[idx];
+                let velocity = &mut self.velocities[idx];
+                let momentum = &mut self.momentum[idx];
+                let gradient = &grads[idx];
 
-            // TODO: bias-corrected momentum and velocity estimates
-            *param -= *momentum / (velocity.sqrt() + EPSILON) * lr + *param * self.lambda * lr;
+                *momentum = BETA1 * *momentum + (1.0 - BETA1) * gradient;
+                *velocity = BETA2 * *velocity + (1.0 - BETA2) * gradient * gradient;
+
+                // TODO: bias-corrected momentum and velocity estimates
+                *param -= *momentum / (velocity.sqrt() + EPSILON) * lr + *param * lambda * lr;
+            }
         }
     }
+
+    fn lr(&self) -> f32 {
+        self.lr
+    }
+
+    fn param_groups_mut(&mut self) -> &mut ParamGroups {
+        &mut self.param_groups
+    }
+
+    fn kind(&self) -> String {
+        "adamw".to_string()
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState {
+            kind: self.kind(),
+            step: self.step,
+            buffers: vec![self.momentum.clone(), self.velocities.clone()],
+        }
+    }
+
+    fn check_buffers(&self, buffers: &[Vec<f32>]) -> io::Result<()> {
+        let [momentum, velocities] = buffers else {
+            return Err(invalid_state(
+                "Optimizer state does not have momentum and velocity buffers",
+            ));
+        };
+        if momentum.len() != self.momentum.len() || velocities.len() != self.velocities.len() {
+            return Err(invalid_state(
+                "Optimizer state does not match the number of params",
+            ));
+        }
+        Ok(())
+    }
+
+    fn load_state(&mut self, state: OptimizerState) -> io::Result<()> {
+        self.check_state(&state)?;
+        let [momentum, velocities]: [Vec<f32>; 2] = state.buffers.try_into().unwrap();
+
+        self.step = state.step;
+        self.momentum = momentum;
+        self.velocities = velocities;
+        Ok(())
+    }
 }
@@ -0,0 +1,89 @@
+use std::io;
+
+use crate::{
+    network::Network,
+    optim::{Optimizer, OptimizerState, ParamGroups, invalid_state},
+};
+
+pub struct Lookahead {
+    inner: Box<dyn Optimizer>,
+    k: u32,
+    alpha: f32,
+    step: u64,
+    slow_params: Vec<f32>,
+}
+
+impl Lookahead {
+    pub fn new(inner: Box<dyn Optimizer>, k: u32, alpha: f32, network: &Network) -> Self {
+        assert!(k > 0);
+
+        Self {
+            inner,
+            k,
+            alpha,
+            step: 0,
+            slow_params: network.param_buffer().to_vec(),
+        }
+    }
+}
+
+impl Optimizer for Lookahead {
+    fn update(&mut self, params: &mut [f32], grads: &[f32], batch_size: u32) {
+        self.inner.update(params, grads, batch_size);
+        self.step += 1;
+
+        if !self.step.is_multiple_of(self.k as u64) {
+            return;
+        }
+
+        // pull the slow weights towards the fast weights, then reset the fast weights
+        for (slow, fast) in self.slow_params.iter_mut().zip(params.iter_mut()) {
+            *slow += self.alpha * (*fast - *slow);
+            *fast = *slow;
+        }
+    }
+
+    fn lr(&self) -> f32 {
+        self.inner.lr()
+    }
+
+    fn param_groups_mut(&mut self) -> &mut ParamGroups {
+        self.inner.param_groups_mut()
+    }
+
+    fn kind(&self) -> String {
+        format!("lookahead({})", self.inner.kind())
+    }
+
+    fn state(&self) -> OptimizerState {
+        // the inner optimizer takes exactly one step per lookahead step, so they share a step count
+        let mut state = self.inner.state();
+        state.kind = self.kind();
+        state.buffers.push(self.slow_params.clone());
+        state
+    }
+
+    fn check_buffers(&self, buffers: &[Vec<f32>]) -> io::Result<()> {
+        let (slow_params, inner_buffers) = buffers
+            .split_last()
+            .ok_or_else(|| invalid_state("Optimizer state does not have lookahead slow weights"))?;
+        if slow_params.len() != self.slow_params.len() {
+            return Err(invalid_state(
+                "Optimizer state does not match the number of params",
+            ));
+        }
+        self.inner.check_buffers(inner_buffers)
+    }
+
+    fn load_state(&mut self, mut state: OptimizerState) -> io::Result<()> {
+        self.check_state(&state)?;
+        let slow_params = state.buffers.pop().unwrap();
+
+        let step = state.step;
+        state.kind = self.inner.kind();
+        self.inner.load_state(state)?;
+        self.step = step;
+        self.slow_params = slow_params;
+        Ok(())
+    }
+}
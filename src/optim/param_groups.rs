@@ -0,0 +1,132 @@
+use std::ops::Range;
+
+use crate::{layer::Layer, network::Network};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Weight,
+    Bias,
+}
+
+#[derive(Clone)]
+pub struct ParamGroup {
+    layer: usize,
+    kind: ParamKind,
+    range: Range<usize>,
+    lr_scale: f32,
+    weight_decay: f32,
+    frozen: bool,
+}
+
+impl ParamGroup {
+    pub fn layer(&self) -> usize {
+        self.layer
+    }
+
+    pub fn kind(&self) -> ParamKind {
+        self.kind
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    pub fn lr_scale(&self) -> f32 {
+        self.lr_scale
+    }
+
+    pub fn weight_decay(&self) -> f32 {
+        self.weight_decay
+    }
+
+    pub fn frozen(&self) -> bool {
+        self.frozen
+    }
+}
+
+#[derive(Clone)]
+pub struct ParamGroups {
+    groups: Vec<ParamGroup>,
+}
+
+impl ParamGroups {
+    // one group for the weights and one for the biases of every layer with params
+    // biases are never decayed by default
+    pub fn new(network: &Network, weight_decay: f32) -> Self {
+        let mut groups = Vec::new();
+        for (idx, layer) in network.layers().iter().enumerate() {
+            if let Layer::Dense(dense_layer) = layer {
+                groups.push(ParamGroup {
+                    layer: idx,
+                    kind: ParamKind::Weight,
+                    range: dense_layer.weight_range(),
+                    lr_scale: 1.0,
+                    weight_decay,
+                    frozen: false,
+                });
+                groups.push(ParamGroup {
+                    layer: idx,
+                    kind: ParamKind::Bias,
+                    range: dense_layer.bias_range(),
+                    lr_scale: 1.0,
+                    weight_decay: 0.0,
+                    frozen: false,
+                });
+            }
+        }
+        Self { groups }
+    }
+
+    pub fn groups(&self) -> &[ParamGroup] {
+        &self.groups
+    }
+
+    pub fn trainable_groups(&self) -> impl Iterator<Item = &ParamGroup> {
+        self.groups.iter().filter(|group| !group.frozen)
+    }
+
+    pub fn lr_scale(mut self, layer: usize, kind: ParamKind, lr_scale: f32) -> Self {
+        self.group_mut(layer, kind).lr_scale = lr_scale;
+        self
+    }
+
+    pub fn layer_lr_scale(self, layer: usize, lr_scale: f32) -> Self {
+        self.lr_scale(layer, ParamKind::Weight, lr_scale)
+            .lr_scale(layer, ParamKind::Bias, lr_scale)
+    }
+
+    pub fn weight_decay(mut self, layer: usize, kind: ParamKind, weight_decay: f32) -> Self {
+        self.group_mut(layer, kind).weight_decay = weight_decay;
+        self
+    }
+
+    pub fn exclude_from_decay(self, layer: usize) -> Self {
+        self.weight_decay(layer, ParamKind::Weight, 0.0)
+            .weight_decay(layer, ParamKind::Bias, 0.0)
+    }
+
+    // sets the weight decay of every bias group
+    pub fn bias_decay(mut self, weight_decay: f32) -> Self {
+        for group in &mut self.groups {
+            if group.kind == ParamKind::Bias {
+                group.weight_decay = weight_decay;
+            }
+        }
+        self
+    }
+
+    pub fn set_frozen(&mut self, layer: usize, frozen: bool) {
+        for group in &mut self.groups {
+            if group.layer == layer {
+                group.frozen = frozen;
+            }
+        }
+    }
+
+    fn group_mut(&mut self, layer: usize, kind: ParamKind) -> &mut ParamGroup {
+        self.groups
+            .iter_mut()
+            .find(|group| group.layer == layer && group.kind == kind)
+            .unwrap_or_else(|| panic!("Layer {} has no parameters", layer))
+    }
+}
@@ -1,20 +1,73 @@
-use crate::optim::Optimizer;
+use std::io;
+
+use crate::{
+    network::Network,
+    optim::{Optimizer, OptimizerState, ParamGroups, invalid_state},
+};
 
 pub struct Sgd {
     lr: f32,
+    param_groups: ParamGroups,
+    step: u64,
 }
 
 impl Sgd {
-    pub fn new(lr: f32) -> Self {
-        Self { lr }
+    pub fn new(lr: f32, network: &Network) -> Self {
+        Self::with_param_groups(lr, ParamGroups::new(network, 0.0))
+    }
+
+    pub fn with_param_groups(lr: f32, param_groups: ParamGroups) -> Self {
+        Self {
+            lr,
+            param_groups,
+            step: 0,
+        }
     }
 }
 
 impl Optimizer for Sgd {
     fn update(&mut self, params: &mut [f32], grads: &[f32], batch_size: u32) {
-        let lr = self.lr / batch_size as f32;
-        for (param, grad) in params.iter_mut().zip(grads.iter()) {
-            *param -= grad * lr;
+        self.step += 1;
+        for group in self.param_groups.trainable_groups() {
+            let lr = self.lr * group.lr_scale() / batch_size as f32;
+            let lambda = group.weight_decay();
+            for idx in group.range() {
+                params[idx] -= grads[idx] * lr + params[idx] * lambda * lr;
+            }
         }
     }
+
+    fn lr(&self) -> f32 {
+        self.lr
+    }
+
+    fn param_groups_mut(&mut self) -> &mut ParamGroups {
+        &mut self.param_groups
+    }
+
+    fn kind(&self) -> String {
+        "sgd".to_string()
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState {
+            kind: self.kind(),
+            step: self.step,
+            buffers: Vec::new(),
+        }
+    }
+
+    fn check_buffers(&self, buffers: &[Vec<f32>]) -> io::Result<()> {
+        if !buffers.is_empty() {
+            return Err(invalid_state("Optimizer state was not written by sgd"));
+        }
+        Ok(())
+    }
+
+    fn load_state(&mut self, state: OptimizerState) -> io::Result<()> {
+        self.check_state(&state)?;
+
+        self.step = state.step;
+        Ok(())
+    }
 }
@@ -0,0 +1,87 @@
+use rand::{Rng, distr::weighted::WeightedIndex, rngs::StdRng, seq::SliceRandom};
+
+use crate::{DataPoint, max_index};
+
+// decides which samples of the dataset an epoch trains on and in which order
+pub trait Sampler {
+    // returns indices into dataset, rng is seeded per epoch by the trainer
+    fn sample(&mut self, dataset: &[DataPoint], rng: &mut StdRng) -> Vec<usize>;
+}
+
+// every sample once per epoch in a new random order, the default
+pub struct ShuffleSampler;
+
+impl Sampler for ShuffleSampler {
+    fn sample(&mut self, dataset: &[DataPoint], rng: &mut StdRng) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..dataset.len()).collect();
+        order.shuffle(rng);
+        order
+    }
+}
+
+// every sample once per epoch in dataset order
+pub struct SequentialSampler;
+
+impl Sampler for SequentialSampler {
+    fn sample(&mut self, dataset: &[DataPoint], _rng: &mut StdRng) -> Vec<usize> {
+        (0..dataset.len()).collect()
+    }
+}
+
+// draws samples with replacement, each with probability proportional to its weight
+pub struct WeightedSampler {
+    distribution: WeightedIndex<f32>,
+    num_weights: usize,
+    num_samples: usize,
+}
+
+impl WeightedSampler {
+    // weights has one entry per sample of the dataset, num_samples is the epoch length
+    pub fn new(weights: &[f32], num_samples: usize) -> Self {
+        Self {
+            distribution: WeightedIndex::new(weights)
+                .expect("Please give weighted sampling non-negative weights with a positive sum"),
+            num_weights: weights.len(),
+            num_samples,
+        }
+    }
+}
+
+impl Sampler for WeightedSampler {
+    fn sample(&mut self, dataset: &[DataPoint], rng: &mut StdRng) -> Vec<usize> {
+        assert!(
+            self.num_weights == dataset.len(),
+            "Sampler has {} weights but the dataset has {} samples",
+            self.num_weights,
+            dataset.len()
+        );
+
+        (0..self.num_samples)
+            .map(|_| rng.sample(&self.distribution))
+            .collect()
+    }
+}
+
+// draws samples with replacement so every class, the max_index of the target, is equally likely
+pub struct ClassBalancedSampler;
+
+impl Sampler for ClassBalancedSampler {
+    fn sample(&mut self, dataset: &[DataPoint], rng: &mut StdRng) -> Vec<usize> {
+        let mut classes: Vec<Vec<usize>> = Vec::new();
+        for (idx, data_pt) in dataset.iter().enumerate() {
+            let class = max_index(&data_pt.target);
+            if class >= classes.len() {
+                classes.resize(class + 1, Vec::new());
+            }
+            classes[class].push(idx);
+        }
+        classes.retain(|samples| !samples.is_empty());
+
+        (0..dataset.len())
+            .map(|_| {
+                let samples = &classes[rng.random_range(0..classes.len())];
+                samples[rng.random_range(0..samples.len())]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,140 @@
+mod png;
+mod proto;
+mod record;
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::tensorboard::proto::ProtoWriter;
+
+// writes tensorboard event files, view them with `tensorboard --logdir <dir>`
+pub struct SummaryWriter {
+    writer: BufWriter<File>,
+}
+
+impl SummaryWriter {
+    const NUM_HISTOGRAM_BUCKETS: usize = 30;
+
+    // starts a new event file in dir
+    pub fn new(dir: impl AsRef<Path>) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+        let file_name = format!(
+            "events.out.tfevents.{}.{}.{}",
+            wall_time() as u64,
+            host,
+            process::id()
+        );
+        let mut writer = Self {
+            writer: BufWriter::new(File::create(dir.as_ref().join(file_name))?),
+        };
+
+        // every event file starts with its version
+        let mut event = ProtoWriter::new();
+        event.double(1, wall_time());
+        event.string(3, "brain.Event:2");
+        record::write_record(&mut writer.writer, &event.into_bytes())?;
+        Ok(writer)
+    }
+
+    pub fn add_scalar(&mut self, tag: &str, value: f32, step: u64) -> io::Result<()> {
+        let mut summary_value = ProtoWriter::new();
+        summary_value.string(1, tag);
+        summary_value.float(2, value);
+        self.write_summary(summary_value, step)
+    }
+
+    // equal width buckets between the smallest and largest value
+    pub fn add_histogram(&mut self, tag: &str, values: &[f32], step: u64) -> io::Result<()> {
+        let min = values.iter().copied().fold(f32::INFINITY, f32::min) as f64;
+        let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max) as f64;
+
+        let mut bucket_limits = Vec::new();
+        let mut buckets = Vec::new();
+        if !values.is_empty() {
+            let width = (max - min) / Self::NUM_HISTOGRAM_BUCKETS as f64;
+            let num_buckets = if width > 0.0 {
+                Self::NUM_HISTOGRAM_BUCKETS
+            } else {
+                1
+            };
+            bucket_limits = (1..=num_buckets)
+                .map(|idx| min + idx as f64 * width)
+                .collect();
+            // rounding can leave the last limit just below max
+            bucket_limits[num_buckets - 1] = max;
+
+            buckets = vec![0.0; num_buckets];
+            for &value in values {
+                let idx = if width > 0.0 {
+                    ((value as f64 - min) / width) as usize
+                } else {
+                    0
+                };
+                buckets[idx.min(num_buckets - 1)] += 1.0;
+            }
+        }
+
+        let mut histogram = ProtoWriter::new();
+        histogram.double(1, if values.is_empty() { 0.0 } else { min });
+        histogram.double(2, if values.is_empty() { 0.0 } else { max });
+        histogram.double(3, values.len() as f64);
+        histogram.double(4, values.iter().map(|&value| value as f64).sum());
+        histogram.double(5, values.iter().map(|&value| (value * value) as f64).sum());
+        histogram.packed_doubles(6, &bucket_limits);
+        histogram.packed_doubles(7, &buckets);
+
+        let mut summary_value = ProtoWriter::new();
+        summary_value.string(1, tag);
+        summary_value.message(5, histogram);
+        self.write_summary(summary_value, step)
+    }
+
+    // pixels are grayscale values in 0..=1, row by row
+    pub fn add_image(
+        &mut self,
+        tag: &str,
+        pixels: &[f32],
+        width: u32,
+        height: u32,
+        step: u64,
+    ) -> io::Result<()> {
+        let mut image = ProtoWriter::new();
+        image.int(1, height as i64);
+        image.int(2, width as i64);
+        // one color channel
+        image.int(3, 1);
+        image.bytes(4, &png::encode_grayscale(pixels, width, height));
+
+        let mut summary_value = ProtoWriter::new();
+        summary_value.string(1, tag);
+        summary_value.message(4, image);
+        self.write_summary(summary_value, step)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn write_summary(&mut self, summary_value: ProtoWriter, step: u64) -> io::Result<()> {
+        let mut summary = ProtoWriter::new();
+        summary.message(1, summary_value);
+
+        let mut event = ProtoWriter::new();
+        event.double(1, wall_time());
+        event.int(2, step as i64);
+        event.message(5, summary);
+        record::write_record(&mut self.writer, &event.into_bytes())
+    }
+}
+
+fn wall_time() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |duration| duration.as_secs_f64())
+}
@@ -0,0 +1,105 @@
+use crate::tensorboard::record::crc32;
+
+// encodes pixels in 0..=1, row by row, as an 8-bit grayscale png
+// the image data is stored without compression, which keeps the encoder tiny
+pub fn encode_grayscale(pixels: &[f32], width: u32, height: u32) -> Vec<u8> {
+    assert!(pixels.len() == (width * height) as usize);
+
+    // every scanline starts with filter type 0
+    let mut scanlines = Vec::with_capacity(((width + 1) * height) as usize);
+    for row in pixels.chunks(width as usize) {
+        scanlines.push(0);
+        scanlines.extend(
+            row.iter()
+                .map(|pixel| (pixel.clamp(0.0, 1.0) * 255.0).round() as u8),
+        );
+    }
+
+    let mut ihdr = Vec::new();
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // bit depth 8, grayscale, deflate, no filtering, no interlacing
+    ihdr.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    write_chunk(&mut png, b"IHDR", &ihdr);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// zlib stream made of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK_LEN: usize = 0xffff;
+
+    let mut zlib = vec![0x78, 0x01];
+    let num_blocks = data.len().div_ceil(MAX_BLOCK_LEN).max(1);
+    for idx in 0..num_blocks {
+        let block = &data[idx * MAX_BLOCK_LEN..((idx + 1) * MAX_BLOCK_LEN).min(data.len())];
+        let is_final = idx == num_blocks - 1;
+        zlib.push(is_final as u8);
+        zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(data).to_be_bytes());
+    zlib
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+
+    let mut a = 1;
+    let mut b = 0;
+    for &byte in data {
+        a = (a + byte as u32) % MOD;
+        b = (b + a) % MOD;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_have_known_crcs() {
+        let png = encode_grayscale(&[0.0, 1.0], 2, 1);
+        assert!(png[..8] == [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']);
+
+        // (kind, data, crc) of every chunk, crcs computed with zlib
+        let expected: [(&[u8; 4], &[u8], u32); 3] = [
+            (
+                b"IHDR",
+                &[0, 0, 0, 2, 0, 0, 0, 1, 8, 0, 0, 0, 0],
+                0xd149_2056,
+            ),
+            (
+                b"IDAT",
+                &[
+                    0x78, 0x01, 1, 3, 0, 0xfc, 0xff, 0, 0, 0xff, 0x01, 0x02, 0x01, 0x00,
+                ],
+                0xb701_2ef7,
+            ),
+            (b"IEND", &[], 0xae42_6082),
+        ];
+        let mut rest = &png[8..];
+        for (kind, data, crc) in expected {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            assert!(&rest[4..8] == kind);
+            assert!(&rest[8..8 + len] == data);
+            assert!(rest[8 + len..12 + len] == crc.to_be_bytes());
+            rest = &rest[12 + len..];
+        }
+        assert!(rest.is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+// just enough of the protobuf wire format to encode tensorboard events
+
+const VARINT: u32 = 0;
+const FIXED64: u32 = 1;
+const LEN: u32 = 2;
+const FIXED32: u32 = 5;
+
+pub struct ProtoWriter {
+    buffer: Vec<u8>,
+}
+
+impl ProtoWriter {
+    pub fn new() -> Self {
+        Self { buffer: Vec::new() }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buffer.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u32) {
+        self.varint(((field << 3) | wire_type) as u64);
+    }
+
+    pub fn int(&mut self, field: u32, value: i64) {
+        self.key(field, VARINT);
+        self.varint(value as u64);
+    }
+
+    pub fn double(&mut self, field: u32, value: f64) {
+        self.key(field, FIXED64);
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn float(&mut self, field: u32, value: f32) {
+        self.key(field, FIXED32);
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, LEN);
+        self.varint(value.len() as u64);
+        self.buffer.extend_from_slice(value);
+    }
+
+    pub fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    pub fn message(&mut self, field: u32, message: ProtoWriter) {
+        self.bytes(field, &message.buffer);
+    }
+
+    pub fn packed_doubles(&mut self, field: u32, values: &[f64]) {
+        let data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        self.bytes(field, &data);
+    }
+}
@@ -0,0 +1,59 @@
+use std::io::{self, Write};
+
+// reflected polynomials of crc32c (castagnoli), used by tfrecord, and crc32, used by png
+const CRC32C_POLY: u32 = 0x82f6_3b78;
+const CRC32_POLY: u32 = 0xedb8_8320;
+
+fn crc(poly: u32, data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (poly & mask);
+        }
+    }
+    !crc
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    crc(CRC32_POLY, data)
+}
+
+fn masked_crc32c(data: &[u8]) -> u32 {
+    let crc = crc(CRC32C_POLY, data);
+    crc.rotate_right(15).wrapping_add(0xa282_ead8)
+}
+
+// tfrecord framing: length, masked crc of the length, data, masked crc of the data
+pub fn write_record(writer: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    let len = (data.len() as u64).to_le_bytes();
+    writer.write_all(&len)?;
+    writer.write_all(&masked_crc32c(&len).to_le_bytes())?;
+    writer.write_all(data)?;
+    writer.write_all(&masked_crc32c(data).to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crcs_match_check_values() {
+        assert!(crc(CRC32C_POLY, b"123456789") == 0xe306_9283);
+        assert!(crc32(b"123456789") == 0xcbf4_3926);
+    }
+
+    #[test]
+    fn record_is_framed_with_masked_crcs() {
+        let mut record = Vec::new();
+        write_record(&mut record, b"hello").unwrap();
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&5u64.to_le_bytes());
+        expected.extend_from_slice(&0x3e04_b2eau32.to_le_bytes());
+        expected.extend_from_slice(b"hello");
+        expected.extend_from_slice(&0x191c_1fbbu32.to_le_bytes());
+        assert!(record == expected);
+    }
+}
@@ -1,28 +1,115 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    mem,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use indicatif::ProgressBar;
+use rand::{SeedableRng, rngs::StdRng};
+use wincode_derive::{SchemaRead, SchemaWrite};
+
 use crate::{
-    DataPoint,
+    DataPoint, Pair, Triplet,
+    average::{Ema, Swa},
+    callback::{
+        BatchEndFn, BatchMetrics, Callback, EarlyStopping, EpochEndFn, EpochMetrics,
+        ModelCheckpoint, Monitor,
+    },
+    eval::{self, EvalReport},
+    health::{self, DenseHealth, HealthReport, NonFiniteError, NonFiniteSource, ReluHealth},
     layer::Layer,
-    loss::{CrossEntropy, Loss, Mse},
+    loss::{
+        BinaryCrossEntropy, ClosureLoss, CompositeLoss, ContrastiveLoss, CrossEntropy, FocalLoss,
+        Huber, KlDivergence, L1, LogCosh, Loss, Mse, Reduction, SigmoidMse, SmoothL1, TripletLoss,
+    },
+    max_index,
     network::Network,
-    optim::{Adam, AdamW, Optimizer, Sgd},
+    optim::{Adam, AdamW, Lookahead, Optimizer, OptimizerState, ParamGroups, Sgd},
+    sampler::{ClassBalancedSampler, Sampler, SequentialSampler, ShuffleSampler, WeightedSampler},
 };
 
+type Augmenter = Box<dyn Fn(&[f32]) -> Vec<f32>>;
+
+fn all_finite(values: &[f32]) -> bool {
+    values.iter().all(|value| value.is_finite())
+}
+
+#[derive(SchemaRead, SchemaWrite)]
+struct Checkpoint {
+    network: Network,
+    optimizer: OptimizerState,
+    epoch: u32,
+    batch: u32,
+    seed: u64,
+    // Callback::state of every callback, in order
+    callbacks: Vec<Vec<u8>>,
+    ema: Option<Vec<f32>>,
+    // params and number of averaged epochs
+    swa: Option<(Vec<f32>, u32)>,
+    // accumulated batches, samples and gradients of an update still in progress
+    accumulated: Option<(u32, u32, Vec<f32>)>,
+}
+
+// blends the hard target loss with matching the softened outputs of a frozen teacher
+struct Distillation {
+    teacher: Network,
+    loss_fn: KlDivergence,
+    alpha: f32,
+    soft_target_buffer: Vec<f32>,
+    grad_buffer: Vec<f32>,
+}
+
 pub struct Trainer {
     network: Network,
     batch_size: u32,
+    // the last batch of an epoch can be smaller than batch_size
+    loaded_samples: u32,
+    drop_last: bool,
+    sampler: Box<dyn Sampler>,
+    show_progress: bool,
+    check_finite: bool,
+    non_finite: Option<NonFiniteError>,
+    accumulation_steps: u32,
+    accumulated_batches: u32,
+    accumulated_samples: u32,
+    // number of samples whose gradients are summed in param_grad_buffer
+    grad_samples: u32,
     param_grad_buffer: Vec<f32>,
+    trainable: Vec<bool>,
     value_buffer: Vec<Vec<f32>>,
     value_grad_buffer: Vec<Vec<f32>>,
     target_buffer: Vec<f32>,
     loss_fn: Box<dyn Loss>,
+    reduction: Reduction,
     optimizer: Box<dyn Optimizer>,
+    ema: Option<Ema>,
+    swa: Option<Swa>,
+    // set by fit so swa only averages its last epochs
+    total_epochs: Option<u32>,
+    distillation: Option<Distillation>,
+    augmenter: Option<Augmenter>,
+    callbacks: Vec<Box<dyn Callback>>,
+    // states of the callbacks while fit has them taken out, for checkpoints taken by them
+    taken_callback_states: Option<Vec<Vec<u8>>>,
+    last_loss: f32,
+    last_accuracy: f32,
+    epoch: u32,
+    batch: u32,
+    seed: u64,
 }
 
 impl Trainer {
     fn new(
         network: Network,
         batch_size: u32,
+        accumulation_steps: u32,
         loss_fn: Box<dyn Loss>,
+        reduction: Reduction,
         optimizer: Box<dyn Optimizer>,
+        seed: u64,
     ) -> Self {
         let mut value_buffer = Vec::with_capacity(network.layers().len() + 1);
         value_buffer.push(vec![
@@ -34,15 +121,40 @@ impl Trainer {
         }
 
         let num_params = network.num_params();
+        let num_layers = network.layers().len();
         Self {
             network: network,
             batch_size,
+            loaded_samples: 0,
+            drop_last: false,
+            sampler: Box::new(ShuffleSampler),
+            show_progress: true,
+            check_finite: false,
+            non_finite: None,
+            accumulation_steps,
+            accumulated_batches: 0,
+            accumulated_samples: 0,
+            grad_samples: 0,
             param_grad_buffer: vec![0.0; num_params as usize],
+            trainable: vec![true; num_layers],
             value_buffer: value_buffer.clone(),
             value_grad_buffer: value_buffer.clone(),
             target_buffer: value_buffer.last().unwrap().clone(),
             loss_fn,
+            reduction,
             optimizer,
+            ema: None,
+            swa: None,
+            total_epochs: None,
+            distillation: None,
+            augmenter: None,
+            callbacks: Vec::new(),
+            taken_callback_states: None,
+            last_loss: 0.0,
+            last_accuracy: 0.0,
+            epoch: 0,
+            batch: 0,
+            seed,
         }
     }
 
@@ -50,123 +162,947 @@ impl Trainer {
         &self.network
     }
 
+    pub fn network_mut(&mut self) -> &mut Network {
+        &mut self.network
+    }
+
     pub fn loss_fn(&self) -> &dyn Loss {
         self.loss_fn.as_ref()
     }
 
+    pub fn reduction(&self) -> Reduction {
+        self.reduction
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    // number of batches already run in the current epoch
+    pub fn batch_index(&self) -> u32 {
+        self.batch
+    }
+
+    pub fn finish_epoch(&mut self) {
+        // don't carry a partially accumulated update into the next epoch
+        if self.accumulated_batches > 0 {
+            self.update();
+        }
+
+        self.epoch += 1;
+        self.batch = 0;
+
+        // without fit every epoch is averaged
+        if let Some(swa) = &mut self.swa
+            && self
+                .total_epochs
+                .is_none_or(|total_epochs| self.epoch + swa.num_epochs() > total_epochs)
+        {
+            swa.update(self.network.param_buffer());
+        }
+    }
+
+    pub fn ema_network(&self) -> Option<Network> {
+        let ema = self.ema.as_ref()?;
+        let mut network = self.network.clone();
+        network.param_buffer_mut().copy_from_slice(ema.params());
+        Some(network)
+    }
+
+    pub fn swa_network(&self) -> Option<Network> {
+        let params = self.swa.as_ref()?.params()?;
+        let mut network = self.network.clone();
+        network.param_buffer_mut().copy_from_slice(params);
+        Some(network)
+    }
+
+    // deterministic per epoch, so shuffling with it is reproducible after resuming
+    pub fn epoch_rng(&self, epoch: u32) -> StdRng {
+        StdRng::seed_from_u64(self.seed.wrapping_add(epoch as u64))
+    }
+
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.checkpoint()?)
+    }
+
+    // the trainer must be built with the same network layout and optimizer as the checkpoint
+    pub fn resume_from(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.load_checkpoint(&fs::read(path)?)
+    }
+
+    // in-memory version of save_checkpoint
+    // taken from inside a callback, it holds the callback states from the start of the epoch
+    pub fn checkpoint(&self) -> io::Result<Vec<u8>> {
+        let checkpoint = Checkpoint {
+            network: self.network.clone(),
+            optimizer: self.optimizer.state(),
+            epoch: self.epoch,
+            batch: self.batch,
+            seed: self.seed,
+            callbacks: match &self.taken_callback_states {
+                Some(states) => states.clone(),
+                None => self
+                    .callbacks
+                    .iter()
+                    .map(|callback| callback.state())
+                    .collect(),
+            },
+            ema: self.ema.as_ref().map(|ema| ema.params().to_vec()),
+            swa: self.swa.as_ref().and_then(|swa| {
+                let params = swa.params()?;
+                Some((params.to_vec(), swa.num_averaged()))
+            }),
+            accumulated: (self.accumulated_batches > 0).then(|| {
+                (
+                    self.accumulated_batches,
+                    self.accumulated_samples,
+                    self.param_grad_buffer.clone(),
+                )
+            }),
+        };
+        wincode::serialize(&checkpoint).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+    }
+
+    pub fn load_checkpoint(&mut self, data: &[u8]) -> io::Result<()> {
+        let checkpoint = wincode::deserialize::<Checkpoint>(data)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+        // same kind and sizes for every layer, so the params land where they came from
+        let layers_match = checkpoint.network.layers().len() == self.network.layers().len()
+            && checkpoint
+                .network
+                .layers()
+                .iter()
+                .zip(self.network.layers())
+                .all(|(saved, layer)| {
+                    mem::discriminant(saved) == mem::discriminant(layer)
+                        && saved.input_size() == layer.input_size()
+                        && saved.output_size() == layer.output_size()
+                });
+        if !layers_match || checkpoint.network.num_params() != self.network.num_params() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Checkpoint network does not match the trainer's network",
+            ));
+        }
+        if let Some((batches, _, grads)) = &checkpoint.accumulated
+            && (*batches >= self.accumulation_steps || grads.len() != self.param_grad_buffer.len())
+        {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Checkpoint gradient accumulation does not match the trainer's",
+            ));
+        }
+        if checkpoint.callbacks.len() != self.callbacks.len() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Checkpoint callbacks do not match the trainer's callbacks",
+            ));
+        }
+        self.optimizer.check_state(&checkpoint.optimizer)?;
+        for (callback, state) in self.callbacks.iter().zip(&checkpoint.callbacks) {
+            callback.check_state(state)?;
+        }
+
+        // everything is checked, so nothing below leaves the trainer half loaded
+        self.optimizer.load_state(checkpoint.optimizer)?;
+        for (callback, state) in self.callbacks.iter_mut().zip(&checkpoint.callbacks) {
+            callback.load_state(state)?;
+        }
+
+        self.network = checkpoint.network;
+        // averages missing from the checkpoint start over from the loaded weights
+        if let Some(ema) = &mut self.ema {
+            match checkpoint.ema {
+                Some(params) if params.len() == self.network.param_buffer().len() => {
+                    ema.reset(&params)
+                }
+                _ => ema.reset(self.network.param_buffer()),
+            }
+        }
+        if let Some(swa) = &mut self.swa {
+            match checkpoint.swa {
+                Some((params, num_averaged))
+                    if params.len() == self.network.param_buffer().len() =>
+                {
+                    swa.load(params, num_averaged)
+                }
+                _ => swa.load(Vec::new(), 0),
+            }
+        }
+        match checkpoint.accumulated {
+            Some((batches, samples, grads)) => {
+                self.accumulated_batches = batches;
+                self.accumulated_samples = samples;
+                self.grad_samples = samples;
+                self.param_grad_buffer = grads;
+            }
+            None => {
+                self.accumulated_batches = 0;
+                self.accumulated_samples = 0;
+            }
+        }
+        self.epoch = checkpoint.epoch;
+        self.batch = checkpoint.batch;
+        self.seed = checkpoint.seed;
+        Ok(())
+    }
+
+    pub fn is_trainable(&self, layer: usize) -> bool {
+        self.trainable[layer]
+    }
+
+    pub fn set_trainable(&mut self, layer: usize, trainable: bool) {
+        self.trainable[layer] = trainable;
+        self.optimizer
+            .param_groups_mut()
+            .set_frozen(layer, !trainable);
+    }
+
     pub fn run_batch_augmented<F>(&mut self, batch: &[DataPoint], augmenter: F)
     where
         F: Fn(&[f32]) -> Vec<f32>,
     {
-        assert!(batch.len() == self.batch_size as usize);
+        self.assert_batch_len(batch.len());
 
+        self.loaded_samples = batch.len() as u32;
         for (idx, data_pt) in batch.iter().enumerate() {
             self.value_buffer[0][idx * data_pt.input.len()..(idx + 1) * data_pt.input.len()]
                 .copy_from_slice(&augmenter(&data_pt.input));
         }
 
         self.forward_all();
+        self.load_targets(batch.iter().map(|data_pt| data_pt.target.as_slice()));
+        self.step(batch.len() as u32, None);
+    }
 
-        let output_size = self.network.layers().last().unwrap().output_size();
-        for (idx, data_pt) in batch.iter().enumerate() {
-            self.target_buffer[idx * output_size as usize..(idx + 1) * output_size as usize]
-                .copy_from_slice(&data_pt.target);
+    pub fn run_batch(&mut self, batch: &[DataPoint]) {
+        self.assert_batch_len(batch.len());
+
+        self.load_inputs(batch.iter().map(|data_pt| data_pt.input.as_slice()));
+
+        self.forward_all();
+        self.load_targets(batch.iter().map(|data_pt| data_pt.target.as_slice()));
+        self.step(batch.len() as u32, None);
+    }
+
+    // weights scales the loss of each sample in the batch
+    pub fn run_batch_weighted(&mut self, batch: &[DataPoint], weights: &[f32]) {
+        self.assert_batch_len(batch.len());
+        assert!(weights.len() == batch.len());
+
+        self.load_inputs(batch.iter().map(|data_pt| data_pt.input.as_slice()));
+
+        self.forward_all();
+        self.load_targets(batch.iter().map(|data_pt| data_pt.target.as_slice()));
+        self.step(batch.len() as u32, Some(weights));
+    }
+
+    // mean loss of the last batch run through a step, including triplet and pair batches
+    pub fn last_loss(&self) -> f32 {
+        self.last_loss
+    }
+
+    // fraction of the last batch where the max_index of the output matches the target's
+    pub fn last_accuracy(&self) -> f32 {
+        self.last_accuracy
+    }
+
+    pub fn lr(&self) -> f32 {
+        self.optimizer.lr()
+    }
+
+    // l2 norm of param_grads, per sample when the reduction is the mean
+    pub fn grad_norm(&self) -> f32 {
+        health::norm(&self.param_grad_buffer) * self.grad_scale()
+    }
+
+    // turns the summed gradients in param_grad_buffer into what the optimizer applies
+    pub fn grad_scale(&self) -> f32 {
+        match self.reduction {
+            Reduction::Mean => 1.0 / self.grad_samples.max(1) as f32,
+            Reduction::Sum => 1.0,
         }
-        self.backward();
-        self.update(batch.len() as u32);
     }
 
-    pub fn run_batch(&mut self, batch: &[DataPoint]) {
-        assert!(batch.len() == self.batch_size as usize);
+    // set by the last step when it failed the finite check, cleared by every other step
+    pub fn non_finite_error(&self) -> Option<NonFiniteError> {
+        self.non_finite
+    }
 
-        for (idx, data_pt) in batch.iter().enumerate() {
-            self.value_buffer[0][idx * data_pt.input.len()..(idx + 1) * data_pt.input.len()]
-                .copy_from_slice(&data_pt.input);
+    // per layer statistics of the last batch run through a step, none before the first batch
+    pub fn health(&self) -> Option<HealthReport> {
+        if self.loaded_samples == 0 {
+            return None;
+        }
+
+        let params = self.network.param_buffer();
+        let grad_scale = self.grad_scale();
+        let mut dense = Vec::new();
+        let mut relu = Vec::new();
+        for (idx, layer) in self.network.layers().iter().enumerate() {
+            match layer {
+                Layer::Dense(dense_layer) => dense.push(DenseHealth {
+                    layer: idx,
+                    weight_norm: health::norm(&params[dense_layer.weight_range()]),
+                    grad_norm: health::norm(&self.param_grad_buffer[dense_layer.weight_range()])
+                        * grad_scale,
+                }),
+                Layer::ReLu(relu_layer) => {
+                    let size = relu_layer.size() as usize;
+                    let outputs = &self.value_buffer[idx + 1][..self.loaded_len(idx + 1)];
+                    let num_dead = (0..size)
+                        .filter(|&unit| outputs.chunks(size).all(|row| row[unit] <= 0.0))
+                        .count();
+                    relu.push(ReluHealth {
+                        layer: idx,
+                        dead_fraction: num_dead as f32 / size as f32,
+                    });
+                }
+            }
+        }
+        Some(HealthReport { dense, relu })
+    }
+
+    pub fn add_callback(&mut self, callback: impl Callback + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    // trains until `epochs` epochs are finished, so a resumed trainer picks up where it left off
+    // the sampler picks the samples of every epoch using epoch_rng
+    // with check_finite set, training stops at the first step that fails the check
+    pub fn fit(
+        &mut self,
+        train: &[DataPoint],
+        val: Option<&[DataPoint]>,
+        epochs: u32,
+    ) -> Result<(), NonFiniteError> {
+        self.total_epochs = Some(epochs);
+        while self.epoch < epochs {
+            // also checked before the first epoch, a resumed trainer may have stopped already
+            if self.callbacks.iter().any(|callback| callback.should_stop()) {
+                break;
+            }
+
+            // taken out so the callbacks can look at the trainer while being called
+            let mut callbacks = mem::take(&mut self.callbacks);
+            self.taken_callback_states =
+                Some(callbacks.iter().map(|callback| callback.state()).collect());
+            let result = self.fit_epoch(train, val, &mut callbacks);
+            self.taken_callback_states = None;
+            callbacks.append(&mut self.callbacks);
+            self.callbacks = callbacks;
+            result?;
+
+            for callback in &self.callbacks {
+                callback.after_epoch_end(self);
+            }
+        }
+        Ok(())
+    }
+
+    fn fit_epoch(
+        &mut self,
+        train: &[DataPoint],
+        val: Option<&[DataPoint]>,
+        callbacks: &mut [Box<dyn Callback>],
+    ) -> Result<(), NonFiniteError> {
+        let epoch = self.epoch;
+        let mut order = self.sampler.sample(train, &mut self.epoch_rng(epoch));
+        if self.drop_last {
+            order.truncate(order.len() - order.len() % self.batch_size as usize);
+        }
+        let num_batches = order.len().div_ceil(self.batch_size as usize) as u32;
+        assert!(num_batches > 0, "Sampler returned less than one batch");
+
+        let start_time = Instant::now();
+        let first_batch = self.batch;
+        let mut total_loss = 0.0;
+        let mut total_correct = 0.0;
+
+        let bar = if self.show_progress {
+            ProgressBar::new(num_batches as u64)
+        } else {
+            ProgressBar::hidden()
+        };
+        bar.inc(first_batch as u64);
+        for batch in order
+            .chunks(self.batch_size as usize)
+            .skip(first_batch as usize)
+        {
+            let batch_start_time = Instant::now();
+            self.run_indexed_batch(train, batch);
+            if let Some(err) = self.non_finite {
+                bar.abandon();
+                return Err(err);
+            }
+            total_loss += self.last_loss * batch.len() as f32;
+            total_correct += self.last_accuracy * batch.len() as f32;
+
+            let metrics = BatchMetrics {
+                epoch,
+                batch: self.batch - 1,
+                num_batches,
+                loss: self.last_loss,
+                accuracy: self.last_accuracy,
+                lr: self.lr(),
+                grad_norm: self.grad_norm(),
+                samples_per_second: batch.len() as f64 / batch_start_time.elapsed().as_secs_f64(),
+            };
+            for callback in callbacks.iter_mut() {
+                callback.on_batch_end(self, &metrics);
+            }
+            bar.inc(1);
+        }
+        self.finish_epoch();
+        bar.finish();
+
+        let seconds = start_time.elapsed().as_secs_f64();
+        let val = val.map(|val| self.evaluate(val));
+        let samples_run = order.len() - (first_batch * self.batch_size) as usize;
+        let metrics = EpochMetrics {
+            epoch,
+            loss: total_loss / samples_run.max(1) as f32,
+            accuracy: total_correct / samples_run.max(1) as f32,
+            lr: self.lr(),
+            val,
+            seconds,
+            samples_per_second: samples_run as f64 / seconds,
+        };
+        for callback in callbacks.iter_mut() {
+            callback.on_epoch_end(self, &metrics);
+        }
+        Ok(())
+    }
+
+    // top-5 accuracy, or top-1 for networks with fewer outputs
+    pub fn evaluate(&self, dataset: &[DataPoint]) -> EvalReport {
+        let num_classes = self.network.layers().last().unwrap().output_size();
+        self.evaluate_top_k(dataset, num_classes.min(5))
+    }
+
+    pub fn evaluate_top_k(&self, dataset: &[DataPoint], top_k: u32) -> EvalReport {
+        eval::evaluate(
+            &self.network,
+            self.loss_fn.as_ref(),
+            dataset,
+            self.batch_size,
+            top_k,
+        )
+    }
+
+    // batch holds indices into dataset
+    fn run_indexed_batch(&mut self, dataset: &[DataPoint], batch: &[usize]) {
+        match &self.augmenter {
+            Some(augmenter) => {
+                self.loaded_samples = batch.len() as u32;
+                for (idx, &data_idx) in batch.iter().enumerate() {
+                    let input = augmenter(&dataset[data_idx].input);
+                    self.value_buffer[0][idx * input.len()..(idx + 1) * input.len()]
+                        .copy_from_slice(&input);
+                }
+            }
+            None => {
+                self.load_inputs(batch.iter().map(|&idx| dataset[idx].input.as_slice()));
+            }
         }
 
         self.forward_all();
+        self.load_targets(batch.iter().map(|&idx| dataset[idx].target.as_slice()));
+        self.step(batch.len() as u32, None);
+    }
 
-        let output_size = self.network.layers().last().unwrap().output_size();
-        for (idx, data_pt) in batch.iter().enumerate() {
-            self.target_buffer[idx * output_size as usize..(idx + 1) * output_size as usize]
-                .copy_from_slice(&data_pt.target);
+    fn load_targets<'a>(&mut self, targets: impl Iterator<Item = &'a [f32]>) {
+        for (idx, target) in targets.enumerate() {
+            self.target_buffer[idx * target.len()..(idx + 1) * target.len()]
+                .copy_from_slice(target);
+        }
+    }
+
+    // runs the network once on each of the anchors, positives and negatives
+    // and sums the gradients of all three passes into one update
+    pub fn run_triplet_batch(&mut self, batch: &[Triplet], loss_fn: &TripletLoss) {
+        self.assert_batch_len(batch.len());
+
+        let anchor_values = self.embed(batch.iter().map(|triplet| triplet.anchor.as_slice()));
+        let positive_values = self.embed(batch.iter().map(|triplet| triplet.positive.as_slice()));
+        let negative_values = self.embed(batch.iter().map(|triplet| triplet.negative.as_slice()));
+        let anchors = self.embeddings(&anchor_values);
+        let positives = self.embeddings(&positive_values);
+        let negatives = self.embeddings(&negative_values);
+
+        let mut anchor_grads = vec![0.0; anchors.len()];
+        let mut positive_grads = vec![0.0; positives.len()];
+        let mut negative_grads = vec![0.0; negatives.len()];
+        let embedding_size = anchors.len() / batch.len();
+        let mut total_loss = 0.0;
+        for idx in 0..batch.len() {
+            let row = idx * embedding_size..(idx + 1) * embedding_size;
+            total_loss += loss_fn.forward(
+                &anchors[row.clone()],
+                &positives[row.clone()],
+                &negatives[row.clone()],
+            );
+            loss_fn.backward(
+                &anchors[row.clone()],
+                &positives[row.clone()],
+                &negatives[row.clone()],
+                &mut anchor_grads[row.clone()],
+                &mut positive_grads[row.clone()],
+                &mut negative_grads[row],
+            );
+        }
+
+        self.last_loss = total_loss / batch.len() as f32;
+
+        let non_finite =
+            self.find_non_finite_forward(&[&anchor_values, &positive_values, &negative_values]);
+        self.begin_step();
+        self.backward_embeddings(anchor_values, &anchor_grads);
+        self.backward_embeddings(positive_values, &positive_grads);
+        self.backward_embeddings(negative_values, &negative_grads);
+        self.finish_step(batch.len() as u32, non_finite);
+    }
+
+    pub fn run_pair_batch(&mut self, batch: &[Pair], loss_fn: &ContrastiveLoss) {
+        self.assert_batch_len(batch.len());
+
+        let first_values = self.embed(batch.iter().map(|pair| pair.first.as_slice()));
+        let second_values = self.embed(batch.iter().map(|pair| pair.second.as_slice()));
+        let firsts = self.embeddings(&first_values);
+        let seconds = self.embeddings(&second_values);
+
+        let mut first_grads = vec![0.0; firsts.len()];
+        let mut second_grads = vec![0.0; seconds.len()];
+        let embedding_size = firsts.len() / batch.len();
+        let mut total_loss = 0.0;
+        for (idx, pair) in batch.iter().enumerate() {
+            let row = idx * embedding_size..(idx + 1) * embedding_size;
+            total_loss +=
+                loss_fn.forward(&firsts[row.clone()], &seconds[row.clone()], pair.similar);
+            loss_fn.backward(
+                &firsts[row.clone()],
+                &seconds[row.clone()],
+                pair.similar,
+                &mut first_grads[row.clone()],
+                &mut second_grads[row],
+            );
+        }
+
+        self.last_loss = total_loss / batch.len() as f32;
+
+        let non_finite = self.find_non_finite_forward(&[&first_values, &second_values]);
+        self.begin_step();
+        self.backward_embeddings(first_values, &first_grads);
+        self.backward_embeddings(second_values, &second_grads);
+        self.finish_step(batch.len() as u32, non_finite);
+    }
+
+    // summed hard target loss of the batch, without computing gradients
+    // the distillation term is not included
+    pub fn batch_loss(&mut self, batch: &[DataPoint]) -> f32 {
+        self.assert_batch_len(batch.len());
+
+        self.load_inputs(batch.iter().map(|data_pt| data_pt.input.as_slice()));
+        self.forward_all();
+        self.load_targets(batch.iter().map(|data_pt| data_pt.target.as_slice()));
+        let len = self.loaded_len(self.value_buffer.len() - 1);
+        self.loss_fn.forward_batch(
+            &self.value_buffer.last().unwrap()[..len],
+            &self.target_buffer[..len],
+            None,
+            Reduction::Sum,
+            self.loaded_samples,
+        )
+    }
+
+    // fills param_grads and input_grads with the gradients of the summed batch loss
+    // without updating the network
+    pub fn compute_gradients(&mut self, batch: &[DataPoint]) {
+        self.assert_batch_len(batch.len());
+
+        self.load_inputs(batch.iter().map(|data_pt| data_pt.input.as_slice()));
+        self.forward_all();
+        self.load_targets(batch.iter().map(|data_pt| data_pt.target.as_slice()));
+        self.param_grad_buffer.fill(0.0);
+        self.grad_samples = self.loaded_samples;
+        self.loss_backward(None);
+        self.backward_layers(0);
+    }
+
+    pub fn is_distilling(&self) -> bool {
+        self.distillation.is_some()
+    }
+
+    pub fn param_grads(&self) -> &[f32] {
+        &self.param_grad_buffer
+    }
+
+    pub fn input_grads(&self) -> &[f32] {
+        &self.value_grad_buffer[0][..self.loaded_len(0)]
+    }
+
+    // whether fit skips the last batch of an epoch when it is smaller than batch_size
+    pub fn set_drop_last(&mut self, drop_last: bool) {
+        self.drop_last = drop_last;
+    }
+
+    // whether fit draws a progress bar
+    pub fn set_show_progress(&mut self, show_progress: bool) {
+        self.show_progress = show_progress;
+    }
+
+    // whether every step checks the loss, activations and gradients for NaN and infinity
+    // a step that fails the check is not applied, see non_finite_error
+    pub fn set_check_finite(&mut self, check_finite: bool) {
+        self.check_finite = check_finite;
+    }
+
+    fn assert_batch_len(&self, len: usize) {
+        assert!(
+            len > 0 && len <= self.batch_size as usize,
+            "Batch of {} samples does not fit the trainer's batch size {}",
+            len,
+            self.batch_size
+        );
+    }
+
+    // length of the part of value_buffer[idx] that holds the loaded samples
+    fn loaded_len(&self, idx: usize) -> usize {
+        self.value_buffer[idx].len() / self.batch_size as usize * self.loaded_samples as usize
+    }
+
+    fn load_inputs<'a>(&mut self, inputs: impl Iterator<Item = &'a [f32]>) {
+        let mut loaded_samples = 0;
+        for (idx, input) in inputs.enumerate() {
+            self.value_buffer[0][idx * input.len()..(idx + 1) * input.len()].copy_from_slice(input);
+            loaded_samples += 1;
+        }
+        self.loaded_samples = loaded_samples;
+    }
+
+    // runs the inputs forward and returns a copy of value_buffer to backpropagate from later
+    fn embed<'a>(&mut self, inputs: impl Iterator<Item = &'a [f32]>) -> Vec<Vec<f32>> {
+        self.load_inputs(inputs);
+        self.forward_all();
+        self.value_buffer.clone()
+    }
+
+    // the outputs of the loaded samples in values returned by embed
+    fn embeddings<'a>(&self, values: &'a [Vec<f32>]) -> &'a [f32] {
+        &values.last().unwrap()[..self.loaded_len(values.len() - 1)]
+    }
+
+    // values must come from embed on a batch of the same size
+    fn backward_embeddings(&mut self, values: Vec<Vec<f32>>, output_grads: &[f32]) {
+        let Some(first_trainable) = self.first_trainable_layer() else {
+            return;
+        };
+
+        self.value_buffer = values;
+        let len = self.loaded_len(self.value_buffer.len() - 1);
+        self.value_grad_buffer.last_mut().unwrap()[..len].copy_from_slice(output_grads);
+        self.backward_layers(first_trainable);
+    }
+
+    fn step(&mut self, batch_size: u32, weights: Option<&[f32]>) {
+        let len = self.loaded_len(self.value_buffer.len() - 1);
+        self.last_loss = self.loss_fn.forward_batch(
+            &self.value_buffer.last().unwrap()[..len],
+            &self.target_buffer[..len],
+            weights,
+            Reduction::Mean,
+            self.loaded_samples,
+        );
+        self.last_accuracy = self.batch_accuracy();
+
+        let non_finite = self.find_non_finite_forward(&[&self.value_buffer]);
+        self.begin_step();
+        self.backward(weights);
+        self.finish_step(batch_size, non_finite);
+    }
+
+    // the first NaN or infinity in the inputs, activations or loss of a step
+    // values holds the value_buffer of every forward pass of the step
+    fn find_non_finite_forward(&self, values: &[&[Vec<f32>]]) -> Option<NonFiniteSource> {
+        if !self.check_finite {
+            return None;
+        }
+
+        for values in values {
+            if !all_finite(&values[0][..self.loaded_len(0)]) {
+                return Some(NonFiniteSource::Inputs);
+            }
+            for idx in 0..self.network.layers().len() {
+                if !all_finite(&values[idx + 1][..self.loaded_len(idx + 1)]) {
+                    return Some(NonFiniteSource::Activations(idx));
+                }
+            }
+        }
+        if !self.last_loss.is_finite() {
+            return Some(NonFiniteSource::Loss);
+        }
+        None
+    }
+
+    // applies the step unless it or its gradients failed the finite check
+    fn finish_step(&mut self, batch_size: u32, non_finite: Option<NonFiniteSource>) {
+        let source = non_finite.or_else(|| self.find_non_finite_grads());
+        self.non_finite = source.map(|source| NonFiniteError {
+            epoch: self.epoch,
+            batch: self.batch,
+            source,
+        });
+        if self.non_finite.is_some() {
+            // drop the accumulated gradients instead of applying them
+            self.accumulated_batches = 0;
+            self.accumulated_samples = 0;
+            self.batch += 1;
+            return;
+        }
+        self.end_step(batch_size);
+    }
+
+    fn find_non_finite_grads(&self) -> Option<NonFiniteSource> {
+        if !self.check_finite {
+            return None;
+        }
+
+        // gradients flow from the last layer down
+        for (idx, layer) in self.network.layers().iter().enumerate().rev() {
+            if let Layer::Dense(dense_layer) = layer
+                && !all_finite(&self.param_grad_buffer[dense_layer.param_buffer_range()])
+            {
+                return Some(NonFiniteSource::Gradients(idx));
+            }
+        }
+        None
+    }
+
+    fn batch_accuracy(&self) -> f32 {
+        let len = self.loaded_len(self.value_buffer.len() - 1);
+        let output_size = len / self.loaded_samples as usize;
+        let num_correct = self.value_buffer.last().unwrap()[..len]
+            .chunks(output_size)
+            .zip(self.target_buffer[..len].chunks(output_size))
+            .filter(|(output, target)| max_index(output) == max_index(target))
+            .count();
+        num_correct as f32 / self.loaded_samples as f32
+    }
+
+    fn begin_step(&mut self) {
+        if self.accumulated_batches == 0 {
+            self.param_grad_buffer.fill(0.0);
+        }
+    }
+
+    fn end_step(&mut self, batch_size: u32) {
+        self.accumulated_batches += 1;
+        self.accumulated_samples += batch_size;
+        self.grad_samples = self.accumulated_samples;
+        self.batch += 1;
+
+        if self.accumulated_batches == self.accumulation_steps {
+            self.update();
         }
-        self.backward();
-        self.update(batch.len() as u32);
     }
 
     fn forward_all(&mut self) {
         // value_buffer[0] needs to be pre-filled with all the inputs
 
+        let batch_size = self.loaded_samples;
         for (idx, layer) in self.network.layers().iter().enumerate() {
             let (left, right) = self.value_buffer.split_at_mut(idx + 1);
-            let inputs = &left[idx];
-            let outputs = &mut right[0];
+            let inputs = &left[idx][..(layer.input_size() * batch_size) as usize];
+            let outputs = &mut right[0][..(layer.output_size() * batch_size) as usize];
             match layer {
                 Layer::Dense(dense_layer) => dense_layer.forward(
                     &self.network.param_buffer()[dense_layer.param_buffer_range()],
                     inputs,
                     outputs,
-                    self.batch_size,
+                    batch_size,
                 ),
                 Layer::ReLu(relu_layer) => {
-                    relu_layer.forward(inputs, outputs, self.batch_size);
+                    relu_layer.forward(inputs, outputs, batch_size);
                 }
             }
         }
     }
 
-    fn backward(&mut self) {
-        self.loss_fn.backward(
-            self.value_buffer.last().unwrap(),
-            &self.target_buffer,
-            self.value_grad_buffer.last_mut().unwrap(),
+    fn backward(&mut self, weights: Option<&[f32]>) {
+        // nothing below the lowest trainable layer needs gradients
+        let Some(first_trainable) = self.first_trainable_layer() else {
+            return;
+        };
+
+        self.loss_backward(weights);
+        self.backward_layers(first_trainable);
+    }
+
+    fn loss_backward(&mut self, weights: Option<&[f32]>) {
+        let input_len = self.loaded_len(0);
+        let len = self.loaded_len(self.value_buffer.len() - 1);
+        let outputs = &self.value_buffer.last().unwrap()[..len];
+        let output_grads = &mut self.value_grad_buffer.last_mut().unwrap()[..len];
+
+        // gradients are summed here, the optimizer takes the mean if needed
+        self.loss_fn.backward_batch(
+            outputs,
+            &self.target_buffer[..len],
+            output_grads,
+            weights,
+            Reduction::Sum,
+            self.loaded_samples,
         );
+        if let Some(distillation) = &mut self.distillation {
+            distillation.backward(
+                &self.value_buffer[0][..input_len],
+                outputs,
+                output_grads,
+                weights,
+                self.loaded_samples,
+            );
+        }
+    }
+
+    // value_grad_buffer's last entry needs to be pre-filled with the output gradients
+    // gradients are propagated down to the inputs of lowest_layer
+    fn backward_layers(&mut self, lowest_layer: usize) {
+        let batch_size = self.loaded_samples;
         for (idx, layer) in self.network.layers().iter().enumerate().rev() {
+            if idx < lowest_layer {
+                break;
+            }
+            let input_len = (layer.input_size() * batch_size) as usize;
+            let output_len = (layer.output_size() * batch_size) as usize;
             let (left, right) = self.value_grad_buffer.split_at_mut(idx + 1);
-            let output_grads = &right[0];
-            let input_grads = &mut left[idx];
+            let output_grads = &right[0][..output_len];
+            let input_grads = &mut left[idx][..input_len];
+            let inputs = &self.value_buffer[idx][..input_len];
             match layer {
                 Layer::Dense(dense_layer) => {
                     let layer_params =
                         &self.network.param_buffer()[dense_layer.param_buffer_range()];
                     let layer_grads = &mut self.param_grad_buffer[dense_layer.param_buffer_range()];
 
-                    dense_layer.backward(
+                    if self.trainable[idx] {
+                        dense_layer.accumulate_param_grads(
+                            output_grads,
+                            inputs,
+                            layer_grads,
+                            batch_size,
+                        );
+                    }
+                    dense_layer.backward_inputs(
                         layer_params,
                         output_grads,
-                        &self.value_buffer[idx],
-                        layer_grads,
                         input_grads,
-                        self.batch_size,
+                        batch_size,
                     );
                 }
                 Layer::ReLu(relu_layer) => {
-                    relu_layer.backward(
-                        output_grads,
-                        &self.value_buffer[idx],
-                        input_grads,
-                        self.batch_size,
-                    );
+                    relu_layer.backward(output_grads, inputs, input_grads, batch_size);
                 }
             }
         }
     }
 
-    fn update(&mut self, batch_size: u32) {
+    fn first_trainable_layer(&self) -> Option<usize> {
+        self.network
+            .layers()
+            .iter()
+            .enumerate()
+            .position(|(idx, layer)| self.trainable[idx] && matches!(layer, Layer::Dense(_)))
+    }
+
+    fn update(&mut self) {
+        let batch_size = match self.reduction {
+            Reduction::Mean => self.accumulated_samples,
+            Reduction::Sum => 1,
+        };
         self.optimizer.update(
             &mut self.network.param_buffer_mut(),
             &self.param_grad_buffer,
             batch_size,
         );
+        self.accumulated_batches = 0;
+        self.accumulated_samples = 0;
+
+        if let Some(ema) = &mut self.ema {
+            ema.update(self.network.param_buffer());
+        }
+    }
+}
+
+impl Distillation {
+    fn new(teacher: Network, temperature: f32, alpha: f32, output_buffer_size: usize) -> Self {
+        assert!((0.0..=1.0).contains(&alpha));
+
+        Self {
+            teacher,
+            loss_fn: KlDivergence::new(temperature),
+            alpha,
+            soft_target_buffer: vec![0.0; output_buffer_size],
+            grad_buffer: vec![0.0; output_buffer_size],
+        }
+    }
+
+    // grads holds the hard target gradients and is blended with the distillation gradients
+    fn backward(
+        &mut self,
+        inputs: &[f32],
+        outputs: &[f32],
+        grads: &mut [f32],
+        weights: Option<&[f32]>,
+        batch_size: u32,
+    ) {
+        let teacher_outputs = self.teacher.forward_batch(inputs, batch_size);
+        assert!(teacher_outputs.len() == outputs.len());
+
+        // the buffers are sized for a full batch
+        let soft_targets = &mut self.soft_target_buffer[..outputs.len()];
+        let distill_grads = &mut self.grad_buffer[..outputs.len()];
+
+        let output_size = outputs.len() / batch_size as usize;
+        for (teacher_logits, targets) in teacher_outputs
+            .chunks(output_size)
+            .zip(soft_targets.chunks_mut(output_size))
+        {
+            self.loss_fn.soft_targets(teacher_logits, targets);
+        }
+
+        self.loss_fn.backward_batch(
+            outputs,
+            soft_targets,
+            distill_grads,
+            weights,
+            Reduction::Sum,
+            batch_size,
+        );
+
+        for (grad, distill_grad) in grads.iter_mut().zip(distill_grads.iter()) {
+            *grad = (1.0 - self.alpha) * *grad + self.alpha * distill_grad;
+        }
     }
 }
 
 pub struct TrainerBuilder {
     network: Network,
     batch_size: u32,
+    accumulation_steps: u32,
     loss_fn: Option<Box<dyn Loss>>,
+    reduction: Reduction,
     optimizer: Option<Box<dyn Optimizer>>,
+    frozen_layers: Vec<usize>,
+    seed: Option<u64>,
+    ema_decay: Option<f32>,
+    swa_epochs: Option<u32>,
+    distillation: Option<(Network, f32, f32)>,
+    drop_last: bool,
+    check_finite: bool,
+    sampler: Option<Box<dyn Sampler>>,
+    augmenter: Option<Augmenter>,
+    callbacks: Vec<Box<dyn Callback>>,
 }
 
 impl TrainerBuilder {
@@ -174,20 +1110,54 @@ impl TrainerBuilder {
         Self {
             network,
             batch_size: 0,
+            accumulation_steps: 1,
             optimizer: None,
             loss_fn: None,
+            reduction: Reduction::Mean,
+            frozen_layers: Vec::new(),
+            seed: None,
+            ema_decay: None,
+            swa_epochs: None,
+            distillation: None,
+            drop_last: false,
+            check_finite: false,
+            sampler: None,
+            augmenter: None,
+            callbacks: Vec::new(),
         }
     }
 
     pub fn build(self) -> Trainer {
-        Trainer::new(
+        let mut trainer = Trainer::new(
             self.network,
             self.batch_size,
+            self.accumulation_steps,
             self.loss_fn
                 .expect("Please set a loss function before building a Trainer"),
+            self.reduction,
             self.optimizer
                 .expect("Please set an optimizer before building a Trainer"),
-        )
+            self.seed.unwrap_or_else(rand::random),
+        );
+        for layer in self.frozen_layers {
+            trainer.set_trainable(layer, false);
+        }
+        trainer.ema = self
+            .ema_decay
+            .map(|decay| Ema::new(decay, &trainer.network));
+        trainer.swa = self.swa_epochs.map(Swa::new);
+        trainer.distillation = self.distillation.map(|(teacher, temperature, alpha)| {
+            let output_buffer_size = trainer.target_buffer.len();
+            Distillation::new(teacher, temperature, alpha, output_buffer_size)
+        });
+        trainer.drop_last = self.drop_last;
+        trainer.check_finite = self.check_finite;
+        if let Some(sampler) = self.sampler {
+            trainer.sampler = sampler;
+        }
+        trainer.augmenter = self.augmenter;
+        trainer.callbacks = self.callbacks;
+        trainer
     }
 
     pub fn batch_size(mut self, batch_size: u32) -> Self {
@@ -195,28 +1165,284 @@ impl TrainerBuilder {
         self
     }
 
+    // number of batches whose gradients are summed before each optimizer update
+    pub fn accumulation_steps(mut self, accumulation_steps: u32) -> Self {
+        assert!(accumulation_steps > 0);
+        self.accumulation_steps = accumulation_steps;
+        self
+    }
+
+    // by default the last batch of an epoch keeps the remaining samples even if it is smaller
+    pub fn drop_last(mut self, drop_last: bool) -> Self {
+        self.drop_last = drop_last;
+        self
+    }
+
+    // see Trainer::set_check_finite
+    pub fn check_finite(mut self, check_finite: bool) -> Self {
+        self.check_finite = check_finite;
+        self
+    }
+
+    // fit shuffles the whole training set every epoch unless another sampler is set
+    pub fn sampler(mut self, sampler: impl Sampler + 'static) -> Self {
+        self.sampler = Some(Box::new(sampler));
+        self
+    }
+
+    pub fn sequential_sampling(self) -> Self {
+        self.sampler(SequentialSampler)
+    }
+
+    pub fn class_balanced_sampling(self) -> Self {
+        self.sampler(ClassBalancedSampler)
+    }
+
+    // weights has one entry per training sample, each epoch draws num_samples of them
+    pub fn weighted_sampling(self, weights: &[f32], num_samples: usize) -> Self {
+        self.sampler(WeightedSampler::new(weights, num_samples))
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn freeze_layer(mut self, layer: usize) -> Self {
+        assert!(
+            layer < self.network.layers().len(),
+            "Cannot freeze layer {}, network only has {} layers",
+            layer,
+            self.network.layers().len()
+        );
+        self.frozen_layers.push(layer);
+        self
+    }
+
+    pub fn freeze_layers(mut self, layers: Range<usize>) -> Self {
+        for layer in layers {
+            self = self.freeze_layer(layer);
+        }
+        self
+    }
+
+    // whether each update uses the mean or the sum of the per-sample gradients
+    pub fn reduction(mut self, reduction: Reduction) -> Self {
+        self.reduction = reduction;
+        self
+    }
+
     pub fn mse(mut self) -> Self {
         self.loss_fn = Some(Box::new(Mse::new()));
         self
     }
 
+    pub fn sigmoid_mse(mut self, scale: f32) -> Self {
+        self.loss_fn = Some(Box::new(SigmoidMse::new(scale)));
+        self
+    }
+
+    pub fn l1(mut self) -> Self {
+        self.loss_fn = Some(Box::new(L1::new()));
+        self
+    }
+
+    pub fn huber(mut self, delta: f32) -> Self {
+        self.loss_fn = Some(Box::new(Huber::new(delta)));
+        self
+    }
+
+    pub fn smooth_l1(mut self, beta: f32) -> Self {
+        self.loss_fn = Some(Box::new(SmoothL1::new(beta)));
+        self
+    }
+
+    pub fn log_cosh(mut self) -> Self {
+        self.loss_fn = Some(Box::new(LogCosh::new()));
+        self
+    }
+
     pub fn cross_entropy(mut self) -> Self {
         self.loss_fn = Some(Box::new(CrossEntropy::new()));
         self
     }
 
+    pub fn cross_entropy_with(
+        mut self,
+        label_smoothing: f32,
+        class_weights: Option<Vec<f32>>,
+    ) -> Self {
+        let mut loss_fn = CrossEntropy::new().label_smoothing(label_smoothing);
+        if let Some(class_weights) = class_weights {
+            assert!(class_weights.len() == self.output_size() as usize);
+            loss_fn = loss_fn.class_weights(class_weights);
+        }
+        self.loss_fn = Some(Box::new(loss_fn));
+        self
+    }
+
+    pub fn focal(mut self, gamma: f32, alpha: Option<Vec<f32>>) -> Self {
+        let mut loss_fn = FocalLoss::new(gamma);
+        if let Some(alpha) = alpha {
+            assert!(alpha.len() == self.output_size() as usize);
+            loss_fn = loss_fn.alpha(alpha);
+        }
+        self.loss_fn = Some(Box::new(loss_fn));
+        self
+    }
+
+    pub fn kl_divergence(mut self, temperature: f32) -> Self {
+        self.loss_fn = Some(Box::new(KlDivergence::new(temperature)));
+        self
+    }
+
+    pub fn bce(mut self) -> Self {
+        self.loss_fn = Some(Box::new(BinaryCrossEntropy::new()));
+        self
+    }
+
+    pub fn bce_with_pos_weights(mut self, pos_weights: Vec<f32>) -> Self {
+        assert!(pos_weights.len() == self.output_size() as usize);
+        self.loss_fn = Some(Box::new(BinaryCrossEntropy::with_pos_weights(pos_weights)));
+        self
+    }
+
+    pub fn composite_loss(mut self, loss_fn: CompositeLoss) -> Self {
+        assert!(
+            loss_fn.output_size() <= self.output_size() as usize,
+            "Composite loss ranges need {} outputs, the network only has {}",
+            loss_fn.output_size(),
+            self.output_size()
+        );
+        self.loss_fn = Some(Box::new(loss_fn));
+        self
+    }
+
+    pub fn closure_loss<F, B>(mut self, forward: F, backward: B) -> Self
+    where
+        F: Fn(&[f32], &[f32]) -> f32 + 'static,
+        B: Fn(&[f32], &[f32], &mut [f32]) + 'static,
+    {
+        self.loss_fn = Some(Box::new(ClosureLoss::new(forward, backward)));
+        self
+    }
+
     pub fn adamw(mut self, lr: f32, lambda: f32) -> Self {
         self.optimizer = Some(Box::new(AdamW::new(lr, lambda, &self.network)));
         self
     }
 
+    pub fn adamw_with_groups(mut self, lr: f32, param_groups: ParamGroups) -> Self {
+        self.assert_groups_fit(&param_groups);
+        self.optimizer = Some(Box::new(AdamW::with_param_groups(
+            lr,
+            param_groups,
+            &self.network,
+        )));
+        self
+    }
+
     pub fn adam(mut self, lr: f32) -> Self {
         self.optimizer = Some(Box::new(Adam::new(lr, &self.network)));
         self
     }
 
     pub fn sgd(mut self, lr: f32) -> Self {
-        self.optimizer = Some(Box::new(Sgd::new(lr)));
+        self.optimizer = Some(Box::new(Sgd::new(lr, &self.network)));
+        self
+    }
+
+    pub fn sgd_with_groups(mut self, lr: f32, param_groups: ParamGroups) -> Self {
+        self.assert_groups_fit(&param_groups);
+        self.optimizer = Some(Box::new(Sgd::with_param_groups(lr, param_groups)));
+        self
+    }
+
+    fn assert_groups_fit(&self, param_groups: &ParamGroups) {
+        let num_params = self.network.num_params() as usize;
+        assert!(
+            param_groups
+                .groups()
+                .iter()
+                .all(|group| group.range().end <= num_params),
+            "Please build the param groups from the trainer's network, they don't fit its {} params",
+            num_params
+        );
+    }
+
+    // wraps the optimizer set so far
+    pub fn lookahead(mut self, k: u32, alpha: f32) -> Self {
+        let inner = self
+            .optimizer
+            .take()
+            .expect("Please set an optimizer before wrapping it with lookahead");
+        self.optimizer = Some(Box::new(Lookahead::new(inner, k, alpha, &self.network)));
+        self
+    }
+
+    pub fn ema(mut self, decay: f32) -> Self {
+        self.ema_decay = Some(decay);
+        self
+    }
+
+    // averages the weights at the end of each of the last num_epochs epochs of fit
+    pub fn swa(mut self, num_epochs: u32) -> Self {
+        self.swa_epochs = Some(num_epochs);
+        self
+    }
+
+    // the loss becomes (1 - alpha) * hard target loss + alpha * KL to the teacher's softened outputs
+    pub fn distill(mut self, teacher: Network, temperature: f32, alpha: f32) -> Self {
+        assert!(
+            teacher.layers()[0].input_size() == self.network.layers()[0].input_size(),
+            "Teacher and student networks must have the same input size"
+        );
+        assert!(
+            teacher.layers().last().unwrap().output_size() == self.output_size(),
+            "Teacher and student networks must have the same output size"
+        );
+        self.distillation = Some((teacher, temperature, alpha));
         self
     }
+
+    // applied to each training input by fit
+    pub fn augmenter<F>(mut self, augmenter: F) -> Self
+    where
+        F: Fn(&[f32]) -> Vec<f32> + 'static,
+    {
+        self.augmenter = Some(Box::new(augmenter));
+        self
+    }
+
+    pub fn callback(mut self, callback: impl Callback + 'static) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    pub fn on_batch_end<F>(self, f: F) -> Self
+    where
+        F: FnMut(&Trainer, &BatchMetrics) + 'static,
+    {
+        self.callback(BatchEndFn::new(f))
+    }
+
+    pub fn on_epoch_end<F>(self, f: F) -> Self
+    where
+        F: FnMut(&Trainer, &EpochMetrics) + 'static,
+    {
+        self.callback(EpochEndFn::new(f))
+    }
+
+    pub fn early_stopping(self, monitor: Monitor, patience: u32, min_delta: f32) -> Self {
+        self.callback(EarlyStopping::new(monitor, patience, min_delta))
+    }
+
+    // saves the latest checkpoint and the best network by monitor into dir after every epoch
+    pub fn checkpoint_dir(self, dir: impl Into<PathBuf>, monitor: Monitor) -> Self {
+        self.callback(ModelCheckpoint::new(dir, monitor))
+    }
+
+    fn output_size(&self) -> u32 {
+        self.network.layers().last().unwrap().output_size()
+    }
 }